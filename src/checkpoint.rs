@@ -0,0 +1,88 @@
+/// First byte of every saved state. Bump whenever the layout changes, states
+/// saved by other versions are rejected.
+pub const VERSION: u8 = 2;
+
+#[derive(Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn u32s(&mut self, value: &[u32]) {
+        self.u32(value.len() as u32);
+        for &item in value {
+            self.u32(item);
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        let (&value, rest) = self.buf.split_first()?;
+        self.buf = rest;
+        Some(value)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        let (value, rest) = self.buf.split_first_chunk::<4>()?;
+        self.buf = rest;
+        Some(u32::from_le_bytes(*value))
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        let (value, rest) = self.buf.split_first_chunk::<8>()?;
+        self.buf = rest;
+        Some(u64::from_le_bytes(*value))
+    }
+
+    pub fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        if len > self.buf.len() {
+            return None;
+        }
+        let (value, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Some(value)
+    }
+
+    pub fn u32s(&mut self) -> Option<Vec<u32>> {
+        let len = self.u32()? as usize;
+        if len > self.buf.len() / 4 {
+            return None;
+        }
+        (0..len).map(|_| self.u32()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
@@ -0,0 +1,50 @@
+use core::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+/// Normalize a debug id into the canonical Breakpad form: 32 uppercase hex
+/// digits of the GUID followed by the age in uppercase hex without leading
+/// zeros. UUID spellings (with or without braces, optionally followed by
+/// `-<age>`) and plain 32 to 40 digit identifiers in any case are accepted.
+/// Returns `undefined` for anything else.
+#[wasm_bindgen(js_name = normalizeDebugId)]
+pub fn normalize_debug_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('{')
+        .and_then(|v| v.strip_suffix('}'))
+        .unwrap_or(value);
+
+    let mut digits = String::with_capacity(40);
+    let groups: Vec<&str> = value.split('-').collect();
+    match groups.as_slice() {
+        [plain] => digits.push_str(plain),
+        [a, b, c, d, e, age @ ..]
+            if a.len() == 8
+                && b.len() == 4
+                && c.len() == 4
+                && d.len() == 4
+                && e.len() == 12
+                && age.len() <= 1 =>
+        {
+            for group in groups {
+                digits.push_str(group);
+            }
+        }
+        _ => return None,
+    }
+
+    if digits.len() < 32 || digits.len() > 40 || !digits.bytes().all(|ch| ch.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let (guid, age) = digits.split_at(32);
+    let age = if age.is_empty() {
+        0
+    } else {
+        u32::from_str_radix(age, 16).ok()?
+    };
+
+    let mut out = guid.to_ascii_uppercase();
+    let _ = write!(out, "{age:X}");
+    Some(out)
+}
@@ -0,0 +1,84 @@
+use crate::checkpoint;
+use std::collections::HashMap;
+
+/// Collapses FILE records with identical names onto the first index that
+/// used the name.
+#[derive(Default)]
+pub struct FileDedupe {
+    name: Vec<u8>,
+    names: HashMap<Vec<u8>, u32>,
+    remap: HashMap<u32, u32>,
+}
+
+impl FileDedupe {
+    pub fn on_str_value(&mut self, value: &[u8]) {
+        self.name.extend_from_slice(value);
+    }
+
+    /// Returns the buffered name if `index` is the first FILE record using
+    /// it, or `None` if the record is a duplicate and should be dropped.
+    #[cfg(feature = "file")]
+    pub fn on_file(&mut self, index: u32) -> Option<Vec<u8>> {
+        let name = std::mem::take(&mut self.name);
+        if let Some(&canonical) = self.names.get(&name) {
+            self.remap.insert(index, canonical);
+            return None;
+        }
+        self.names.insert(name.clone(), index);
+        Some(name)
+    }
+
+    #[cfg(feature = "line")]
+    pub fn resolve(&self, index: u32) -> u32 {
+        self.remap.get(&index).copied().unwrap_or(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.name.clear();
+        self.names.clear();
+        self.remap.clear();
+    }
+
+    /// `[index, canonical index]` pairs sorted by index.
+    pub fn mapping(&self) -> Vec<u32> {
+        let mut pairs: Vec<_> = self.remap.iter().collect();
+        pairs.sort_unstable();
+        pairs
+            .into_iter()
+            .flat_map(|(&index, &canonical)| [index, canonical])
+            .collect()
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.bytes(&self.name);
+
+        let mut names: Vec<_> = self.names.iter().collect();
+        names.sort_unstable_by_key(|&(_, &index)| index);
+        w.u32(names.len() as u32);
+        for (name, &index) in names {
+            w.bytes(name);
+            w.u32(index);
+        }
+
+        w.u32s(&self.mapping());
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let name = r.bytes()?.to_vec();
+
+        let len = r.u32()?;
+        let mut names = HashMap::new();
+        for _ in 0..len {
+            let name = r.bytes()?.to_vec();
+            names.insert(name, r.u32()?);
+        }
+
+        let mapping = r.u32s()?;
+        if mapping.len() % 2 != 0 {
+            return None;
+        }
+        let remap = mapping.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+
+        Some(Self { name, names, remap })
+    }
+}
@@ -0,0 +1,115 @@
+use crate::checkpoint;
+use wasm_bindgen::prelude::*;
+
+/// How FUNC records with size 0 are reported.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum EmptyFuncPolicy {
+    /// Report them as point symbols with size 0
+    Keep = 0,
+    /// Don't report them at all
+    Drop = 1,
+    /// Report them once the next FUNC or PUBLIC record starts, with the size
+    /// extended up to its address
+    Extend = 2,
+}
+
+impl EmptyFuncPolicy {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::Keep,
+            1 => Self::Drop,
+            2 => Self::Extend,
+            _ => return None,
+        })
+    }
+}
+
+/// Zero-size FUNC record held back until the address of the next symbol is
+/// known.
+pub struct EmptyFuncs {
+    pub policy: EmptyFuncPolicy,
+    /// Whether the name of the current FUNC record is being held back
+    pub capturing: bool,
+    /// `(addr, params)` of the held back record
+    pending: Option<(u32, u32)>,
+    name: Vec<u8>,
+}
+
+impl EmptyFuncs {
+    pub fn new(policy: EmptyFuncPolicy) -> Self {
+        Self {
+            policy,
+            capturing: false,
+            pending: None,
+            name: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "func")]
+    pub fn start(&mut self) {
+        self.capturing = true;
+        self.name.clear();
+    }
+
+    #[cfg(feature = "func")]
+    pub fn on_str_value(&mut self, value: &[u8]) {
+        self.name.extend_from_slice(value);
+    }
+
+    #[cfg(feature = "func")]
+    pub fn hold(&mut self, addr: u32, params: u32) {
+        self.capturing = false;
+        self.pending = Some((addr, params));
+    }
+
+    /// Returns `(addr, size, params, name)` of the held back record, with the
+    /// size extended to `next_addr` if it follows the record.
+    #[cfg(feature = "func")]
+    pub fn take(&mut self, next_addr: Option<u32>) -> Option<(u32, u32, u32, Vec<u8>)> {
+        let (addr, params) = self.pending.take()?;
+        let size = next_addr.map_or(0, |next| next.saturating_sub(addr));
+        Some((addr, size, params, core::mem::take(&mut self.name)))
+    }
+
+    pub fn clear(&mut self) {
+        self.capturing = false;
+        self.pending = None;
+        self.name.clear();
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.u8(self.policy as u8);
+        w.u8(self.capturing as u8);
+        match self.pending {
+            Some((addr, params)) => {
+                w.u8(1);
+                w.u32(addr);
+                w.u32(params);
+            }
+            None => w.u8(0),
+        }
+        w.bytes(&self.name);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let policy = EmptyFuncPolicy::from_u8(r.u8()?)?;
+        let capturing = match r.u8()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let pending = match r.u8()? {
+            0 => None,
+            1 => Some((r.u32()?, r.u32()?)),
+            _ => return None,
+        };
+        Some(Self {
+            policy,
+            capturing,
+            pending,
+            name: r.bytes()?.to_vec(),
+        })
+    }
+}
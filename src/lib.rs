@@ -1,28 +1,71 @@
+mod checkpoint;
+mod debug_id;
+mod dedupe;
+mod empty;
+mod metadata;
+mod minify;
+#[cfg(feature = "profiling")]
+mod profile;
+mod raw;
+mod records;
+mod scrub;
+mod sha256;
+mod shadow;
+mod strings;
+mod tokenizer;
+mod trace;
+mod utf8;
+mod validate;
+
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 use core::mem;
-use memchr::memchr;
+use memchr::{memchr, memchr_iter};
 use wasm_bindgen::prelude::*;
 
+pub use debug_id::normalize_debug_id;
+pub use empty::EmptyFuncPolicy;
+pub use metadata::Capabilities;
+pub use minify::Minifier;
+pub use raw::RecordKind;
+pub use scrub::Scrubber;
+pub use tokenizer::{TokenApi, TokenKind, Tokenizer};
+pub use validate::{Finding, FindingKind};
+
 #[wasm_bindgen]
 extern "C" {
-    type Api;
+    pub type Api;
 
+    #[cfg(feature = "line")]
     #[wasm_bindgen(method, js_name = onLine)]
     fn on_line(api: &Api, addr: u32, size: u32, line: u32, file_index: u32);
+    #[cfg(feature = "func")]
     #[wasm_bindgen(method, js_name = onFunc)]
     fn on_func(api: &Api, addr: u32, size: u32, params: u32);
+    #[cfg(feature = "file")]
     #[wasm_bindgen(method, js_name = onFile)]
     fn on_file(api: &Api, index: u32);
+    #[cfg(feature = "public")]
     #[wasm_bindgen(method, js_name = onPublic)]
     fn on_public(api: &Api, addr: u32, params: u32);
+    #[wasm_bindgen(method, js_name = onExtension)]
+    fn on_extension(api: &Api, id: u32, rest: &[u8]);
+    #[wasm_bindgen(method, js_name = onNameRepaired)]
+    fn on_name_repaired(api: &Api, offset: f64);
+    #[wasm_bindgen(method, js_name = onRawLine)]
+    fn on_raw_line(api: &Api, kind: RecordKind, bytes: &[u8]);
+
     #[wasm_bindgen(method, js_name = onStrValue)]
     fn on_str_value(api: &Api, value: &[u8]);
+    #[cfg(any(feature = "func", feature = "file", feature = "public"))]
+    #[wasm_bindgen(method, js_name = onStrId)]
+    fn on_str_id(api: &Api, id: u32);
 }
 
 #[allow(dead_code)]
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "profiling", derive(Debug))]
 #[repr(u8)]
 enum State {
     Start = 0,
@@ -54,12 +97,46 @@ enum State {
     PublicHexParams = 20,
     PublicStrName = 21,
     PublicEnd = 22,
+
+    Keyword = 23,
+    ModuleStrLine = 24,
+    InfoStrLine = 25,
+    ExtensionStrLine = 26,
 }
 
+// Must be kept in sync with the last variant
+const STATE_COUNT: usize = State::ExtensionStrLine as usize + 1;
+
 impl State {
     fn next(self) -> Self {
         unsafe { mem::transmute(self as u8 + 1) }
     }
+
+    /// Whether `ch` can start the numeric field parsed in this state, or
+    /// `None` if the state doesn't parse a numeric field.
+    fn starts_field(self, ch: u8) -> Option<bool> {
+        match self {
+            State::LineHexAddr
+            | State::LineHexSize
+            | State::FuncHexAddr
+            | State::FuncHexSize
+            | State::FuncHexParams
+            | State::PublicHexAddr
+            | State::PublicHexParams => Some(hex_value(ch).is_some()),
+            State::LineDecLine | State::LineDecFile | State::FileDecIndex => {
+                Some(dec_value(ch).is_some())
+            }
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        if usize::from(value) < STATE_COUNT {
+            Some(unsafe { mem::transmute::<u8, State>(value) })
+        } else {
+            None
+        }
+    }
 }
 
 const DEC_TABLE: [u8; 256] = {
@@ -104,90 +181,920 @@ const fn hex_value(ch: u8) -> Option<u8> {
     }
 }
 
+const fn is_separator(ch: u8) -> bool {
+    ch == b' ' || ch == b'\t'
+}
+
+// Lines parsed between the clock checks of `parseWithBudget()`
+const LINES_PER_BUDGET_CHECK: usize = 256;
+
+const MAX_KEYWORD_LEN: usize = 16;
+
+/// Extension keywords are uppercase words that fit the keyword buffer and
+/// don't clash with a standard record type.
+fn check_extension_keyword(keyword: &[u8]) -> Result<(), &'static str> {
+    let is_valid = keyword.len() <= MAX_KEYWORD_LEN
+        && keyword.first().is_some_and(u8::is_ascii_uppercase)
+        && keyword
+            .iter()
+            .all(|&ch| matches!(ch, b'A'..=b'Z' | b'0'..=b'9' | b'_'));
+    if !is_valid {
+        return Err("Invalid keyword");
+    }
+    let is_reserved = matches!(
+        keyword,
+        b"MODULE"
+            | b"INFO"
+            | b"FILE"
+            | b"FUNC"
+            | b"PUBLIC"
+            | b"STACK"
+            | b"INLINE"
+            | b"INLINE_ORIGIN"
+    );
+    if is_reserved {
+        return Err("Reserved keyword");
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Counters {
+    files: u64,
+    funcs: u64,
+    lines: u64,
+    publics: u64,
+}
+
 #[wasm_bindgen]
-struct Parser {
+pub struct Parser {
     state: State,
     row: [u32; 4],
     row_pos: u8,
-    api: Api,
+    in_separator: bool,
+    keyword: [u8; MAX_KEYWORD_LEN],
+    keyword_len: u8,
+    extensions: Vec<Vec<u8>>,
+    extension: u32,
+    line: Vec<u8>,
+    module: Vec<u8>,
+    code_id: Vec<u8>,
+    generator: Vec<u8>,
+    seen: metadata::Seen,
+    counters: Counters,
+    parsed: u64,
+    record_start: u64,
+    #[cfg(feature = "profiling")]
+    profile: profile::Profile,
+    trace: Option<trace::Trace>,
+    records: Option<records::Records>,
+    dedupe: Option<dedupe::FileDedupe>,
+    shadow: Option<shadow::FuncRanges>,
+    empty_funcs: Option<empty::EmptyFuncs>,
+    validator: Option<validate::Validator>,
+    raw_lines: Option<raw::RawLines>,
+    utf8: Option<utf8::Utf8Repair>,
+    string_ids: Option<strings::StringTable>,
+    sha256: Option<sha256::Sha256>,
+    expected_sha256: [u8; 32],
+    scratch: Vec<u8>,
+    api: Option<Api>,
 }
 
 #[wasm_bindgen]
 impl Parser {
     #[wasm_bindgen(constructor)]
-    pub fn new(api: Api) -> Self {
+    pub fn new(api: Option<Api>) -> Self {
         Self {
             state: State::Start,
             row: [0; 4],
             row_pos: 0,
+            in_separator: false,
+            keyword: [0; MAX_KEYWORD_LEN],
+            keyword_len: 0,
+            extensions: Vec::new(),
+            extension: 0,
+            line: Vec::new(),
+            module: Vec::new(),
+            code_id: Vec::new(),
+            generator: Vec::new(),
+            seen: metadata::Seen::default(),
+            counters: Counters::default(),
+            parsed: 0,
+            record_start: 0,
+            #[cfg(feature = "profiling")]
+            profile: profile::Profile::default(),
+            trace: None,
+            records: None,
+            dedupe: None,
+            shadow: None,
+            empty_funcs: None,
+            validator: None,
+            raw_lines: None,
+            utf8: None,
+            string_ids: None,
+            sha256: None,
+            expected_sha256: [0; 32],
+            scratch: Vec::new(),
             api,
         }
     }
 
     #[wasm_bindgen]
     pub fn parse(&mut self, chunk: &[u8]) {
-        let mut offset: usize = 0;
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(chunk);
+        }
+
+        match &self.raw_lines {
+            Some(_) => self.parse_lines(chunk),
+            None => self.parse_from(chunk, 0),
+        }
+
+        // Report a record that ends with this chunk right away
+        self.on_pending_end();
+        self.parsed += chunk.len() as u64;
+    }
+
+    /// Same as calling `parse()` for every chunk in order, in a single call.
+    #[wasm_bindgen(js_name = parseVectored)]
+    pub fn parse_vectored(&mut self, chunks: Vec<js_sys::Uint8Array>) {
+        let mut scratch = mem::take(&mut self.scratch);
+        for chunk in chunks {
+            scratch.resize(chunk.length() as usize, 0);
+            chunk.copy_to(&mut scratch);
+            self.parse(&scratch);
+        }
+        self.scratch = scratch;
+    }
+
+    /// Parse `chunk` until it is exhausted or `budget` milliseconds have
+    /// passed, whichever comes first. The clock is checked at line
+    /// boundaries only. Returns the number of bytes consumed, the rest of
+    /// the chunk has to be passed again.
+    #[wasm_bindgen(js_name = parseWithBudget)]
+    pub fn parse_with_budget(&mut self, chunk: &[u8], budget: f64) -> usize {
+        let deadline = js_sys::Date::now() + budget;
+        let mut offset = 0;
         while offset < chunk.len() {
-            offset = match self.state {
-                State::Start => self.parse_start(chunk, offset),
-                State::FuncOrFile => self.parse_func_or_file(chunk, offset),
-
-                State::LineHexAddr => self.parse_hex(chunk, offset),
-                State::LineHexSize => self.parse_hex(chunk, offset),
-                State::FuncHexAddr => self.parse_hex(chunk, offset),
-                State::FuncHexSize => self.parse_hex(chunk, offset),
-                State::FuncHexParams => self.parse_hex(chunk, offset),
-                State::PublicHexAddr => self.parse_hex(chunk, offset),
-                State::PublicHexParams => self.parse_hex(chunk, offset),
-
-                State::LineDecLine => self.parse_dec(chunk, offset),
-                State::LineDecFile => self.parse_dec(chunk, offset),
-                State::FileDecIndex => self.parse_dec(chunk, offset),
-
-                State::FuncStrName => self.parse_str(chunk, offset),
-                State::FileStrName => self.parse_str(chunk, offset),
-                State::PublicStrName => self.parse_str(chunk, offset),
-
-                State::Func => self.skip_until_digit(chunk, offset),
-                State::File => self.skip_until_digit(chunk, offset),
-                State::Public => self.skip_until_digit(chunk, offset),
-
-                State::LineEnd => {
-                    self.on_line_end();
-                    offset
-                }
-                State::FuncEnd => {
-                    self.on_func_end();
-                    offset
-                }
-                State::FileEnd => {
-                    self.on_file_end();
-                    offset
-                }
-                State::PublicEnd => {
-                    self.on_public_end();
-                    offset
-                }
+            let end = match memchr_iter(b'\n', &chunk[offset..]).nth(LINES_PER_BUDGET_CHECK - 1) {
+                Some(i) => offset + i + 1,
+                None => chunk.len(),
+            };
+            self.parse(&chunk[offset..end]);
+            offset = end;
 
-                State::Skip => self.skip_past_newline(chunk, offset),
+            if js_sys::Date::now() >= deadline {
+                break;
             }
         }
+        offset
     }
 
     #[wasm_bindgen]
-    pub fn finish(&mut self) {
+    pub fn finish(&mut self) -> Result<(), JsError> {
+        // Trailing separators at the end of input complete a LINE record
+        if matches!(self.state, State::LineEnd) {
+            self.in_separator = false;
+        }
         match self.state {
-            State::LineEnd => self.on_line_end(),
-            State::FuncEnd => self.on_func_end(),
-            State::FileEnd => self.on_file_end(),
-            State::PublicEnd => self.on_public_end(),
-            _ => (),
+            State::ModuleStrLine | State::InfoStrLine | State::ExtensionStrLine => {
+                self.on_str_line_end()
+            }
+            _ => self.on_pending_end(),
+        }
+        #[cfg(feature = "func")]
+        self.flush_empty_func(None);
+        if let (Some(raw_lines), Some(api)) = (&mut self.raw_lines, &self.api) {
+            raw_lines.finish(|line| api.on_raw_line(raw::classify(line), line));
+        }
+
+        if let Some(validator) = &mut self.validator {
+            if !matches!(self.state, State::Start | State::Skip) {
+                validator.report(FindingKind::Truncated, self.record_start, 0);
+            }
+            validator.finish(!self.module.is_empty());
+        }
+
+        if let Some(sha256) = self.sha256.take() {
+            if sha256.finish() != self.expected_sha256 {
+                return Err(JsError::new("SHA-256 digest mismatch"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Hash every parsed byte and make `finish()` throw if the SHA-256 digest
+    /// of the input does not match `expected` (64 hex characters). Must be
+    /// called before the first `parse()`.
+    #[wasm_bindgen(js_name = verifySha256)]
+    pub fn verify_sha256(&mut self, expected: &str) -> Result<(), JsError> {
+        let expected = expected.as_bytes();
+        if expected.len() != 64 {
+            return Err(JsError::new("Invalid SHA-256 digest"));
+        }
+        for (byte, pair) in self
+            .expected_sha256
+            .iter_mut()
+            .zip(expected.chunks_exact(2))
+        {
+            let (Some(hi), Some(lo)) = (
+                hex_value(pair[0].to_ascii_lowercase()),
+                hex_value(pair[1].to_ascii_lowercase()),
+            ) else {
+                return Err(JsError::new("Invalid SHA-256 digest"));
+            };
+            *byte = (hi << 4) | lo;
+        }
+        self.sha256 = Some(sha256::Sha256::default());
+        Ok(())
+    }
+
+    /// Bytes consumed and number of steps taken in each parser state, as
+    /// `[bytes, steps]` pairs ordered like `profileLabels()`.
+    #[cfg(feature = "profiling")]
+    #[wasm_bindgen]
+    pub fn profile(&self) -> Vec<u64> {
+        self.profile.to_vec()
+    }
+
+    /// Milliseconds spent in each parser state, ordered like
+    /// `profileLabels()`. The clock is read with `Date.now()` at state
+    /// transitions, so short states only show up in aggregate.
+    #[cfg(feature = "profiling")]
+    #[wasm_bindgen(js_name = profileTime)]
+    pub fn profile_time(&self) -> Vec<f64> {
+        self.profile.time()
+    }
+
+    #[cfg(feature = "profiling")]
+    #[wasm_bindgen(js_name = profileLabels)]
+    pub fn profile_labels() -> Vec<String> {
+        (0..STATE_COUNT as u8)
+            .filter_map(State::from_u8)
+            .map(|state| format!("{state:?}"))
+            .collect()
+    }
+
+    fn parse_from(&mut self, chunk: &[u8], mut offset: usize) {
+        #[cfg(feature = "profiling")]
+        self.profile.resume();
+
+        while offset < chunk.len() {
+            #[cfg(feature = "profiling")]
+            let (state, start) = (self.state, offset);
+
+            offset = self.step(chunk, offset);
+
+            #[cfg(feature = "profiling")]
+            self.profile.record(state, self.state, offset - start);
+        }
+
+        #[cfg(feature = "profiling")]
+        self.profile.pause(self.state);
+    }
+
+    /// Parse line by line so that every raw line is reported after its
+    /// record.
+    fn parse_lines(&mut self, chunk: &[u8]) {
+        let mut offset = 0;
+        while offset < chunk.len() {
+            let end = match memchr(b'\n', &chunk[offset..]) {
+                Some(i) => offset + i + 1,
+                None => chunk.len(),
+            };
+
+            // Offsets stay relative to the start of the chunk
+            let exclusive = self.raw_lines.as_ref().is_some_and(|raw| raw.exclusive);
+            if !exclusive {
+                self.parse_from(&chunk[..end], offset);
+                self.on_pending_end();
+            }
+
+            if let (Some(raw_lines), Some(api)) = (&mut self.raw_lines, &self.api) {
+                raw_lines.feed(&chunk[offset..end], |line| {
+                    api.on_raw_line(raw::classify(line), line)
+                });
+            }
+            offset = end;
+        }
+    }
+
+    fn step(&mut self, chunk: &[u8], offset: usize) -> usize {
+        if self.in_separator {
+            return self.skip_separators(chunk, offset);
+        }
+
+        match self.state {
+            State::Start => self.parse_start(chunk, offset),
+            State::FuncOrFile => self.parse_func_or_file(chunk, offset),
+
+            State::LineHexAddr => self.parse_hex(chunk, offset),
+            State::LineHexSize => self.parse_hex(chunk, offset),
+            State::FuncHexAddr => self.parse_hex(chunk, offset),
+            State::FuncHexSize => self.parse_hex(chunk, offset),
+            State::FuncHexParams => self.parse_hex(chunk, offset),
+            State::PublicHexAddr => self.parse_hex(chunk, offset),
+            State::PublicHexParams => self.parse_hex(chunk, offset),
+
+            State::LineDecLine => self.parse_dec(chunk, offset),
+            State::LineDecFile => self.parse_dec(chunk, offset),
+            State::FileDecIndex => self.parse_dec(chunk, offset),
+
+            State::FuncStrName => self.parse_str(chunk, offset),
+            State::FileStrName => self.parse_str(chunk, offset),
+            State::PublicStrName => self.parse_str(chunk, offset),
+
+            State::Func => self.parse_record_keyword(chunk, offset, b"FUNC"),
+            State::File => self.parse_record_keyword(chunk, offset, b"FILE"),
+            State::Public => self.parse_record_keyword(chunk, offset, b"PUBLIC"),
+
+            State::LineEnd => {
+                self.on_line_end();
+                offset
+            }
+            State::FuncEnd => {
+                self.on_func_end();
+                offset
+            }
+            State::FileEnd => {
+                self.on_file_end();
+                offset
+            }
+            State::PublicEnd => {
+                self.on_public_end();
+                offset
+            }
+
+            State::Skip => self.skip_past_newline(chunk, offset),
+
+            State::Keyword => self.parse_keyword(chunk, offset),
+            State::ModuleStrLine => self.parse_str_line(chunk, offset),
+            State::InfoStrLine => self.parse_str_line(chunk, offset),
+            State::ExtensionStrLine => self.parse_str_line(chunk, offset),
+        }
+    }
+
+    /// Number of bytes passed to `parse()` so far.
+    #[wasm_bindgen(js_name = bytesParsed)]
+    pub fn bytes_parsed(&self) -> f64 {
+        self.parsed as f64
+    }
+
+    /// Number of FILE records reported so far.
+    #[wasm_bindgen(js_name = fileCount)]
+    pub fn file_count(&self) -> f64 {
+        self.counters.files as f64
+    }
+
+    /// Number of FUNC records reported so far.
+    #[wasm_bindgen(js_name = funcCount)]
+    pub fn func_count(&self) -> f64 {
+        self.counters.funcs as f64
+    }
+
+    /// Number of LINE records reported so far.
+    #[wasm_bindgen(js_name = lineCount)]
+    pub fn line_count(&self) -> f64 {
+        self.counters.lines as f64
+    }
+
+    /// Number of PUBLIC records reported so far.
+    #[wasm_bindgen(js_name = publicCount)]
+    pub fn public_count(&self) -> f64 {
+        self.counters.publics as f64
+    }
+
+    /// Start recording every reported record into a canonical textual trace
+    /// retrievable with `takeTrace()`.
+    #[wasm_bindgen(js_name = enableTrace)]
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Default::default);
+    }
+
+    /// Return the trace recorded since the last call.
+    #[wasm_bindgen(js_name = takeTrace)]
+    pub fn take_trace(&mut self) -> Option<String> {
+        self.trace.as_mut().map(trace::Trace::take)
+    }
+
+    /// Start collecting every reported record inside the parser. The results
+    /// are available through `getFiles()`, `getFuncs()`, `getLines()`,
+    /// `getPublics()` and the string table returned by `getStrings()` and
+    /// `getStringOffsets()`.
+    #[wasm_bindgen(js_name = enableAccumulate)]
+    pub fn enable_accumulate(&mut self) {
+        self.records.get_or_insert_with(Default::default);
+    }
+
+    /// `[index, name id]` for every FILE record.
+    #[wasm_bindgen(js_name = getFiles)]
+    pub fn get_files(&self) -> Option<Vec<u32>> {
+        self.records.as_ref().map(|r| r.files.clone())
+    }
+
+    /// `[addr, size, params, name id]` for every FUNC record.
+    #[wasm_bindgen(js_name = getFuncs)]
+    pub fn get_funcs(&self) -> Option<Vec<u32>> {
+        self.records.as_ref().map(|r| r.funcs.clone())
+    }
+
+    /// `[addr, size, line, file index]` for every LINE record.
+    #[wasm_bindgen(js_name = getLines)]
+    pub fn get_lines(&self) -> Option<Vec<u32>> {
+        self.records.as_ref().map(|r| r.lines.clone())
+    }
+
+    /// `[addr, params, name id]` for every PUBLIC record.
+    #[wasm_bindgen(js_name = getPublics)]
+    pub fn get_publics(&self) -> Option<Vec<u32>> {
+        self.records.as_ref().map(|r| r.publics.clone())
+    }
+
+    /// Concatenated bytes of all names.
+    #[wasm_bindgen(js_name = getStrings)]
+    pub fn get_strings(&self) -> Option<Vec<u8>> {
+        self.records.as_ref().map(|r| r.strings.bytes.clone())
+    }
+
+    /// Name `id` is `strings.subarray(offsets[id], offsets[id + 1])`.
+    #[wasm_bindgen(js_name = getStringOffsets)]
+    pub fn get_string_offsets(&self) -> Option<Vec<u32>> {
+        self.records.as_ref().map(|r| r.strings.offsets.clone())
+    }
+
+    /// Report only the first FILE record for every distinct name and rewrite
+    /// file indices of LINE records that refer to the dropped duplicates.
+    /// Must be called before the first `parse()`.
+    #[wasm_bindgen(js_name = dedupeFiles)]
+    pub fn dedupe_files(&mut self) {
+        self.dedupe.get_or_insert_with(Default::default);
+    }
+
+    /// `[index, canonical index]` pairs for every dropped duplicate FILE.
+    #[wasm_bindgen(js_name = fileMapping)]
+    pub fn file_mapping(&self) -> Option<Vec<u32>> {
+        self.dedupe.as_ref().map(dedupe::FileDedupe::mapping)
+    }
+
+    /// Drop PUBLIC records whose address falls inside a previously reported
+    /// FUNC record. Must be called before the first `parse()`.
+    #[wasm_bindgen(js_name = dropShadowedPublics)]
+    pub fn drop_shadowed_publics(&mut self) {
+        self.shadow.get_or_insert_with(Default::default);
+    }
+
+    /// Choose how FUNC records with size 0 are reported, they are kept as
+    /// point symbols by default. With `EmptyFuncPolicy.Extend` such a record
+    /// is reported after the LINE records following it. Must be called
+    /// before the first `parse()`.
+    #[wasm_bindgen(js_name = setEmptyFuncPolicy)]
+    pub fn set_empty_func_policy(&mut self, policy: EmptyFuncPolicy) {
+        self.empty_funcs =
+            (policy != EmptyFuncPolicy::Keep).then(|| empty::EmptyFuncs::new(policy));
+    }
+
+    /// Check the structure of the input while parsing. Findings are
+    /// available through `validationReport()` after `finish()`. Must be
+    /// called before the first `parse()`.
+    #[wasm_bindgen(js_name = enableValidation)]
+    pub fn enable_validation(&mut self) {
+        self.validator.get_or_insert_with(Default::default);
+    }
+
+    #[wasm_bindgen(js_name = validationReport)]
+    pub fn validation_report(&self) -> Option<Vec<Finding>> {
+        self.validator.as_ref().map(validate::Validator::findings)
+    }
+
+    /// Report the raw bytes of every line, terminator included, through
+    /// `onRawLine(kind, bytes)` right after the record on it. If `exclusive`
+    /// is true the records aren't parsed at all and only raw lines are
+    /// reported. Must be called before the first `parse()`.
+    #[wasm_bindgen(js_name = enableRawLines)]
+    pub fn enable_raw_lines(&mut self, exclusive: bool) {
+        self.raw_lines
+            .get_or_insert_with(Default::default)
+            .exclusive = exclusive;
+    }
+
+    /// Report lines starting with `keyword` through `onExtension(id, rest)`
+    /// instead of skipping them, where `id` is the returned value and `rest`
+    /// is the remainder of the line after the keyword and a separator.
+    /// Keywords are uppercase words of up to 16 characters that don't clash
+    /// with a standard record type.
+    #[wasm_bindgen(js_name = registerKeyword)]
+    pub fn register_keyword(&mut self, keyword: &str) -> Result<u32, JsError> {
+        let keyword = keyword.as_bytes();
+        check_extension_keyword(keyword).map_err(JsError::new)?;
+
+        if let Some(id) = self.extensions.iter().position(|word| word == keyword) {
+            return Ok(id as u32);
+        }
+        self.extensions.push(keyword.to_vec());
+        Ok(self.extensions.len() as u32 - 1)
+    }
+
+    /// Replace invalid UTF-8 in FUNC, FILE and PUBLIC names with U+FFFD
+    /// before reporting them. `onNameRepaired(offset)` is called with the
+    /// byte offset of every record whose name needed it.
+    #[wasm_bindgen(js_name = enableUtf8Repair)]
+    pub fn enable_utf8_repair(&mut self) {
+        self.utf8.get_or_insert_with(Default::default);
+    }
+
+    /// Keep FUNC, FILE and PUBLIC names inside the parser instead of passing
+    /// them to `onStrValue`. Each record callback is preceded by
+    /// `onStrId(id)` instead, and the name can be fetched later with
+    /// `getString(id)` or `getStringsById(ids)`. Must be called before the
+    /// first `parse()`.
+    #[wasm_bindgen(js_name = enableStringIds)]
+    pub fn enable_string_ids(&mut self) {
+        self.string_ids.get_or_insert_with(Default::default);
+    }
+
+    #[wasm_bindgen(js_name = getString)]
+    pub fn get_string(&self, id: u32) -> Option<Vec<u8>> {
+        self.string_ids.as_ref()?.get(id).map(<[u8]>::to_vec)
+    }
+
+    #[wasm_bindgen(js_name = getStringsById)]
+    pub fn get_strings_by_id(&self, ids: &[u32]) -> Result<Vec<js_sys::Uint8Array>, JsError> {
+        let table = self
+            .string_ids
+            .as_ref()
+            .ok_or_else(|| JsError::new("String ids are not enabled"))?;
+        ids.iter()
+            .map(|&id| {
+                let value = table
+                    .get(id)
+                    .ok_or_else(|| JsError::new("Invalid string id"))?;
+                Ok(js_sys::Uint8Array::from(value))
+            })
+            .collect()
+    }
+
+    /// Prepare the parser for a new input. Enabled options stay enabled and
+    /// allocated buffers are kept for reuse, everything collected so far is
+    /// discarded. A pending `verifySha256()` expectation is dropped too.
+    pub fn reset(&mut self) {
+        self.state = State::Start;
+        self.row = [0; 4];
+        self.row_pos = 0;
+        self.in_separator = false;
+        self.keyword_len = 0;
+        self.line.clear();
+        self.module.clear();
+        self.code_id.clear();
+        self.generator.clear();
+        self.seen = metadata::Seen::default();
+        self.counters = Counters::default();
+        self.parsed = 0;
+        self.record_start = 0;
+        #[cfg(feature = "profiling")]
+        {
+            self.profile = profile::Profile::default();
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.clear();
+        }
+        if let Some(records) = &mut self.records {
+            records.clear();
+        }
+        if let Some(dedupe) = &mut self.dedupe {
+            dedupe.clear();
+        }
+        if let Some(shadow) = &mut self.shadow {
+            shadow.clear();
+        }
+        if let Some(empty_funcs) = &mut self.empty_funcs {
+            empty_funcs.clear();
+        }
+        if let Some(validator) = &mut self.validator {
+            validator.clear();
+        }
+        if let Some(raw_lines) = &mut self.raw_lines {
+            raw_lines.clear();
+        }
+        if let Some(utf8) = &mut self.utf8 {
+            utf8.clear();
+        }
+        if let Some(string_ids) = &mut self.string_ids {
+            string_ids.clear();
+        }
+        self.sha256 = None;
+        self.expected_sha256 = [0; 32];
+    }
+
+    #[wasm_bindgen(js_name = saveState)]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = checkpoint::Writer::default();
+        w.u8(checkpoint::VERSION);
+        w.u8(self.state as u8);
+        w.u8(self.row_pos);
+        for value in self.row {
+            w.u32(value);
+        }
+        w.u8(self.in_separator as u8);
+        w.u8(self.keyword_len);
+        for &ch in &self.keyword {
+            w.u8(ch);
+        }
+        w.u32(self.extensions.len() as u32);
+        for keyword in &self.extensions {
+            w.bytes(keyword);
+        }
+        w.u32(self.extension);
+        w.bytes(&self.line);
+        w.bytes(&self.module);
+        w.bytes(&self.code_id);
+        w.bytes(&self.generator);
+        w.u8(self.seen.inline as u8);
+        w.u8(self.seen.stack_cfi as u8);
+        w.u8(self.seen.stack_win as u8);
+        w.u64(self.counters.files);
+        w.u64(self.counters.funcs);
+        w.u64(self.counters.lines);
+        w.u64(self.counters.publics);
+        w.u64(self.parsed);
+        w.u64(self.record_start);
+        match &self.sha256 {
+            Some(sha256) => {
+                w.u8(1);
+                w.bytes(&self.expected_sha256);
+                sha256.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.records {
+            Some(records) => {
+                w.u8(1);
+                records.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.dedupe {
+            Some(dedupe) => {
+                w.u8(1);
+                dedupe.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.shadow {
+            Some(shadow) => {
+                w.u8(1);
+                shadow.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.validator {
+            Some(validator) => {
+                w.u8(1);
+                validator.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.raw_lines {
+            Some(raw_lines) => {
+                w.u8(1);
+                raw_lines.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.utf8 {
+            Some(utf8) => {
+                w.u8(1);
+                utf8.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.string_ids {
+            Some(string_ids) => {
+                w.u8(1);
+                string_ids.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.trace {
+            Some(trace) => {
+                w.u8(1);
+                trace.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        match &self.empty_funcs {
+            Some(empty_funcs) => {
+                w.u8(1);
+                empty_funcs.save(&mut w);
+            }
+            None => w.u8(0),
+        }
+        w.finish()
+    }
+
+    #[wasm_bindgen(js_name = restoreState)]
+    pub fn restore_state(&mut self, buf: &[u8]) -> Result<(), JsError> {
+        let Some((&checkpoint::VERSION, rest)) = buf.split_first() else {
+            return Err(JsError::new("Unsupported parser state version"));
+        };
+        self.try_restore_state(rest)
+            .ok_or_else(|| JsError::new("Invalid parser state"))
+    }
+
+    #[wasm_bindgen(js_name = moduleOs)]
+    pub fn module_os(&self) -> Option<String> {
+        self.module_field(0)
+    }
+
+    #[wasm_bindgen(js_name = moduleArch)]
+    pub fn module_arch(&self) -> Option<String> {
+        self.module_field(1)
+    }
+
+    /// Debug id as written in the MODULE record, see `normalizeDebugId()`.
+    #[wasm_bindgen(js_name = debugId)]
+    pub fn debug_id(&self) -> Option<String> {
+        self.module_field(2)
+    }
+
+    #[wasm_bindgen(js_name = moduleName)]
+    pub fn module_name(&self) -> Option<String> {
+        self.module_field(3)
+    }
+
+    /// Value of the `INFO CODE_ID` record.
+    #[wasm_bindgen(js_name = codeId)]
+    pub fn code_id(&self) -> Option<String> {
+        (!self.code_id.is_empty()).then(|| String::from_utf8_lossy(&self.code_id).into_owned())
+    }
+
+    /// Producer hints and record types seen so far.
+    #[wasm_bindgen]
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            os: self.module_field(0),
+            arch: self.module_field(1),
+            generator: (!self.generator.is_empty())
+                .then(|| String::from_utf8_lossy(&self.generator).into_owned()),
+            has_code_id: !self.code_id.is_empty(),
+            has_inline: self.seen.inline,
+            has_stack_cfi: self.seen.stack_cfi,
+            has_stack_win: self.seen.stack_win,
         }
     }
 
+    fn try_restore_state(&mut self, buf: &[u8]) -> Option<()> {
+        let mut r = checkpoint::Reader::new(buf);
+        let state = State::from_u8(r.u8()?)?;
+        let row_pos = r.u8()?;
+        if row_pos as usize > self.row.len() {
+            return None;
+        }
+        let mut row = [0; 4];
+        for value in &mut row {
+            *value = r.u32()?;
+        }
+        let in_separator = match r.u8()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let keyword_len = r.u8()?;
+        if keyword_len as usize > self.keyword.len() {
+            return None;
+        }
+        let mut keyword = [0; MAX_KEYWORD_LEN];
+        for ch in &mut keyword {
+            *ch = r.u8()?;
+        }
+        let mut extensions = Vec::new();
+        for _ in 0..r.u32()? {
+            let keyword = r.bytes()?;
+            check_extension_keyword(keyword).ok()?;
+            extensions.push(keyword.to_vec());
+        }
+        let extension = r.u32()?;
+        if matches!(state, State::ExtensionStrLine) && extension as usize >= extensions.len() {
+            return None;
+        }
+        let line = r.bytes()?.to_vec();
+        let module = r.bytes()?.to_vec();
+        let code_id = r.bytes()?.to_vec();
+        let generator = r.bytes()?.to_vec();
+        let mut bool = || match r.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+        let seen = metadata::Seen {
+            inline: bool()?,
+            stack_cfi: bool()?,
+            stack_win: bool()?,
+        };
+        let counters = Counters {
+            files: r.u64()?,
+            funcs: r.u64()?,
+            lines: r.u64()?,
+            publics: r.u64()?,
+        };
+        let parsed = r.u64()?;
+        let record_start = r.u64()?;
+        let sha256 = match r.u8()? {
+            0 => None,
+            1 => {
+                let expected = r.bytes()?.try_into().ok()?;
+                Some((sha256::Sha256::restore(&mut r)?, expected))
+            }
+            _ => return None,
+        };
+        let records = match r.u8()? {
+            0 => None,
+            1 => Some(records::Records::restore(&mut r)?),
+            _ => return None,
+        };
+        let dedupe = match r.u8()? {
+            0 => None,
+            1 => Some(dedupe::FileDedupe::restore(&mut r)?),
+            _ => return None,
+        };
+        let shadow = match r.u8()? {
+            0 => None,
+            1 => Some(shadow::FuncRanges::restore(&mut r)?),
+            _ => return None,
+        };
+        let validator = match r.u8()? {
+            0 => None,
+            1 => Some(validate::Validator::restore(&mut r)?),
+            _ => return None,
+        };
+        let raw_lines = match r.u8()? {
+            0 => None,
+            1 => Some(raw::RawLines::restore(&mut r)?),
+            _ => return None,
+        };
+        let utf8 = match r.u8()? {
+            0 => None,
+            1 => Some(utf8::Utf8Repair::restore(&mut r)?),
+            _ => return None,
+        };
+        let string_ids = match r.u8()? {
+            0 => None,
+            1 => Some(strings::StringTable::restore(&mut r)?),
+            _ => return None,
+        };
+        let trace = match r.u8()? {
+            0 => None,
+            1 => Some(trace::Trace::restore(&mut r)?),
+            _ => return None,
+        };
+        let empty_funcs = match r.u8()? {
+            0 => None,
+            1 => Some(empty::EmptyFuncs::restore(&mut r)?),
+            _ => return None,
+        };
+        if !r.is_empty() {
+            return None;
+        }
+
+        self.state = state;
+        self.row_pos = row_pos;
+        self.row = row;
+        self.in_separator = in_separator;
+        self.keyword_len = keyword_len;
+        self.keyword = keyword;
+        self.extensions = extensions;
+        self.extension = extension;
+        self.line = line;
+        self.module = module;
+        self.code_id = code_id;
+        self.generator = generator;
+        self.seen = seen;
+        self.counters = counters;
+        self.parsed = parsed;
+        self.record_start = record_start;
+        if let Some((sha256, expected)) = sha256 {
+            self.sha256 = Some(sha256);
+            self.expected_sha256 = expected;
+        } else {
+            self.sha256 = None;
+        }
+        self.records = records;
+        self.dedupe = dedupe;
+        self.shadow = shadow;
+        self.validator = validator;
+        self.raw_lines = raw_lines;
+        self.utf8 = utf8;
+        self.string_ids = string_ids;
+        self.trace = trace;
+        self.empty_funcs = empty_funcs;
+        Some(())
+    }
+
     fn parse_start(&mut self, chunk: &[u8], offset: usize) -> usize {
+        self.record_start = self.parsed + offset as u64;
+
         let ch = chunk[offset];
-        if hex_value(ch).is_some() {
+        if cfg!(feature = "line") && hex_value(ch).is_some() {
             self.state = State::LineHexAddr;
 
             // First character is significant
@@ -195,38 +1102,153 @@ impl Parser {
         }
 
         self.state = match ch {
-            b'F' => State::FuncOrFile,
-            b'P' => State::Public,
+            // Empty line
+            b'\r' | b'\n' => State::Start,
+
+            // Might be an extension keyword that shares the first character
+            b'F' | b'P' if self.extensions.iter().any(|word| word.first() == Some(&ch)) => {
+                self.state = State::Keyword;
+                return offset;
+            }
+
+            // Rest of the keyword is checked by the record state
+            b'F' if cfg!(any(feature = "func", feature = "file")) => {
+                self.keyword_len = 1;
+                State::FuncOrFile
+            }
+            b'P' if cfg!(feature = "public") => {
+                self.keyword_len = 1;
+                State::Public
+            }
+
+            b'A'..=b'Z' => {
+                self.state = State::Keyword;
+
+                // First character is significant
+                return offset;
+            }
 
-            // Likely STACK
             _ => State::Skip,
         };
 
         offset + 1
     }
 
+    fn parse_keyword(&mut self, chunk: &[u8], offset: usize) -> usize {
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            if is_separator(ch) || ch == b'\r' || ch == b'\n' {
+                let keyword = &self.keyword[..self.keyword_len as usize];
+                let is_eol = ch == b'\r' || ch == b'\n';
+                if !is_eol && keyword.last() == Some(&b' ') {
+                    continue;
+                }
+                match keyword {
+                    b"STACK" if !is_eol => {
+                        // Record type is in the second word
+                        self.keyword[self.keyword_len as usize] = b' ';
+                        self.keyword_len += 1;
+                        continue;
+                    }
+                    b"INLINE" | b"INLINE_ORIGIN" => self.seen.inline = true,
+                    b"STACK CFI" => self.seen.stack_cfi = true,
+                    b"STACK WIN" => self.seen.stack_win = true,
+                    _ => (),
+                }
+                // Only reached when an extension shares the first character.
+                // The separator is checked by the record state, like on the
+                // fast path.
+                let record = match keyword {
+                    b"FUNC" if cfg!(feature = "func") => Some(State::Func),
+                    b"FILE" if cfg!(feature = "file") => Some(State::File),
+                    b"PUBLIC" if cfg!(feature = "public") => Some(State::Public),
+                    _ => None,
+                };
+                if let Some(state) = record {
+                    self.state = state;
+                    return i;
+                }
+
+                let extension = self.extensions.iter().position(|word| word == keyword);
+                self.state = match (keyword, ch) {
+                    (b"MODULE", b' ' | b'\t') => State::ModuleStrLine,
+                    (b"INFO", b' ' | b'\t') => State::InfoStrLine,
+                    _ if extension.is_some() && !is_eol => State::ExtensionStrLine,
+                    (_, b'\n') => State::Start,
+                    _ => State::Skip,
+                };
+                self.keyword_len = 0;
+                if let Some(id) = extension {
+                    self.extension = id as u32;
+                    if is_eol {
+                        self.on_extension(&[]);
+                    }
+                }
+                return i + 1;
+            }
+
+            let Some(slot) = self.keyword.get_mut(self.keyword_len as usize) else {
+                self.keyword_len = 0;
+                self.state = State::Skip;
+                return i;
+            };
+            *slot = ch;
+            self.keyword_len += 1;
+        }
+        chunk.len()
+    }
+
     fn parse_func_or_file(&mut self, chunk: &[u8], offset: usize) -> usize {
         self.state = match chunk[offset] {
-            b'U' => State::Func,
-            b'I' => State::File,
-            _ => State::Skip,
+            b'U' if cfg!(feature = "func") => State::Func,
+            b'I' if cfg!(feature = "file") => State::File,
+            _ => {
+                self.keyword_len = 0;
+                State::Skip
+            }
         };
-        offset + 1
+        offset
+    }
+
+    /// Matches the rest of `keyword` and a separator after it, then skips to
+    /// the first numeric field. `keyword_len` counts the bytes matched so
+    /// far.
+    fn parse_record_keyword(&mut self, chunk: &[u8], offset: usize, keyword: &[u8]) -> usize {
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            let pos = self.keyword_len as usize;
+            let is_match = match keyword.get(pos) {
+                Some(&expected) => ch == expected,
+                None if pos > keyword.len() => return self.skip_until_digit(chunk, i),
+                // The record ended right after the keyword
+                None if ch == b'\n' || ch == b'\r' => return self.on_error(ch, i),
+                None => is_separator(ch),
+            };
+            if !is_match {
+                // Unknown record type that only shares a prefix
+                self.keyword_len = 0;
+                self.state = State::Skip;
+                return i;
+            }
+            self.keyword_len += 1;
+        }
+        chunk.len()
     }
 
     fn parse_hex(&mut self, chunk: &[u8], offset: usize) -> usize {
-        let mut int_value = self.row[self.row_pos as usize];
-        for i in offset..chunk.len() {
-            let d = match hex_value(chunk[i]) {
+        let Some(&value) = self.row.get(self.row_pos as usize) else {
+            return self.on_error(chunk[offset], offset);
+        };
+
+        let mut int_value = value;
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            let d = match hex_value(ch) {
                 Some(d) => d,
-                None => {
-                    self.row[self.row_pos as usize] = int_value;
-                    self.row_pos += 1;
-                    self.state = self.state.next();
-                    return i + 1;
-                }
+                None => return self.on_field_end(int_value, ch, i),
             };
 
+            // Values wider than 32 bits are rejected rather than truncated
+            if int_value >> 28 != 0 {
+                return self.on_error(ch, i);
+            }
             int_value = (int_value << 4) | u32::from(d);
         }
         self.row[self.row_pos as usize] = int_value;
@@ -234,40 +1256,152 @@ impl Parser {
     }
 
     fn parse_dec(&mut self, chunk: &[u8], offset: usize) -> usize {
-        let mut int_value = self.row[self.row_pos as usize];
-        for i in offset..chunk.len() {
-            let d = match dec_value(chunk[i]) {
+        let Some(&value) = self.row.get(self.row_pos as usize) else {
+            return self.on_error(chunk[offset], offset);
+        };
+
+        let mut int_value = value;
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            let d = match dec_value(ch) {
                 Some(d) => d,
-                None => {
-                    self.row[self.row_pos as usize] = int_value;
-                    self.row_pos += 1;
-                    self.state = self.state.next();
-                    return i + 1;
-                }
+                None => return self.on_field_end(int_value, ch, i),
             };
 
-            int_value = (int_value * 10) + u32::from(d);
+            let Some(next) = int_value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u32::from(d)))
+            else {
+                return self.on_error(ch, i);
+            };
+            int_value = next;
         }
         self.row[self.row_pos as usize] = int_value;
         chunk.len()
     }
 
+    fn on_field_end(&mut self, int_value: u32, ch: u8, offset: usize) -> usize {
+        // Only the last field of a LINE record may be terminated by the end
+        // of line, every other numeric field must be followed by a separator.
+        // Trailing separators after the last field are checked once skipped.
+        let is_last = matches!(self.state, State::LineDecFile);
+        let is_eol = ch == b'\n' || ch == b'\r';
+        let is_valid = is_separator(ch) || is_last && is_eol;
+        if !is_valid {
+            return self.on_error(ch, offset);
+        }
+
+        self.row[self.row_pos as usize] = int_value;
+        self.row_pos += 1;
+        self.in_separator = is_separator(ch);
+        self.state = self.state.next();
+
+        #[cfg(feature = "public")]
+        if let (State::PublicStrName, Some(shadow)) = (self.state, &mut self.shadow) {
+            if shadow.contains(self.row[0]) {
+                // Covered by a FUNC record, skip before reporting the name
+                self.on_end();
+                self.state = State::Skip;
+            }
+        }
+
+        #[cfg(feature = "func")]
+        match (self.state, &mut self.empty_funcs) {
+            // Address of the next symbol is known, held back FUNC record can
+            // be reported
+            (State::FuncHexSize | State::PublicHexParams, Some(_)) => {
+                self.flush_empty_func(Some(self.row[0]));
+            }
+            (State::FuncStrName, Some(empty_funcs)) if self.row[1] == 0 => {
+                match empty_funcs.policy {
+                    // Skip before reporting the name
+                    EmptyFuncPolicy::Drop => {
+                        self.on_end();
+                        self.state = State::Skip;
+                    }
+                    EmptyFuncPolicy::Extend => empty_funcs.start(),
+                    EmptyFuncPolicy::Keep => (),
+                }
+            }
+            _ => (),
+        }
+        offset + 1
+    }
+
+    fn on_error(&mut self, ch: u8, offset: usize) -> usize {
+        // Drop the malformed record and resynchronize at the next line
+        if let Some(validator) = &mut self.validator {
+            let kind = FindingKind::MalformedRecord;
+            validator.report(kind, self.record_start, 0);
+        }
+        self.on_end();
+        if ch != b'\n' {
+            self.state = State::Skip;
+        }
+        offset + 1
+    }
+
     fn parse_str(&mut self, chunk: &[u8], offset: usize) -> usize {
         if let Some(i) = memchr(b'\n', &chunk[offset..]) {
+            self.on_name(&chunk[offset..(offset + i)], true);
             self.state = self.state.next();
-            self.api.on_str_value(&chunk[offset..(offset + i)]);
             return offset + i + 1;
         }
-        self.api.on_str_value(&chunk[offset..chunk.len()]);
+        self.on_name(&chunk[offset..chunk.len()], false);
+        chunk.len()
+    }
+
+    fn on_name(&mut self, value: &[u8], is_end: bool) {
+        let Some(mut utf8) = self.utf8.take() else {
+            return self.on_str_value(value);
+        };
+        self.on_str_value(utf8.feed(value, is_end));
+        if is_end && utf8.take_repaired() {
+            if let Some(api) = &self.api {
+                api.on_name_repaired(self.record_start as f64);
+            }
+        }
+        self.utf8 = Some(utf8);
+    }
+
+    fn parse_str_line(&mut self, chunk: &[u8], offset: usize) -> usize {
+        if let Some(i) = memchr(b'\n', &chunk[offset..]) {
+            self.line.extend_from_slice(&chunk[offset..(offset + i)]);
+            self.on_str_line_end();
+            return offset + i + 1;
+        }
+        self.line.extend_from_slice(&chunk[offset..chunk.len()]);
+        chunk.len()
+    }
+
+    fn skip_separators(&mut self, chunk: &[u8], offset: usize) -> usize {
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            if !is_separator(ch) {
+                self.in_separator = false;
+
+                // Numeric field is missing, or there is more data after a
+                // complete LINE record
+                let is_eol = ch == b'\n' || ch == b'\r';
+                let is_extra = matches!(self.state, State::LineEnd) && !is_eol;
+                if is_extra || self.state.starts_field(ch) == Some(false) {
+                    return self.on_error(ch, i);
+                }
+                return i;
+            }
+        }
         chunk.len()
     }
 
     fn skip_until_digit(&mut self, chunk: &[u8], offset: usize) -> usize {
-        for i in offset..chunk.len() {
-            if hex_value(chunk[i]).is_some() {
+        for (i, &ch) in chunk.iter().enumerate().skip(offset) {
+            if hex_value(ch).is_some() {
+                self.keyword_len = 0;
                 self.state = self.state.next();
                 return i;
             }
+            // The record ended before its first field
+            if ch == b'\n' || ch == b'\r' {
+                return self.on_error(ch, i);
+            }
         }
         chunk.len()
     }
@@ -280,28 +1414,233 @@ impl Parser {
         chunk.len()
     }
 
+    fn on_pending_end(&mut self) {
+        match self.state {
+            // Not complete until the end of line follows the separators
+            State::LineEnd if self.in_separator => (),
+            State::LineEnd => self.on_line_end(),
+            State::FuncEnd => self.on_func_end(),
+            State::FileEnd => self.on_file_end(),
+            State::PublicEnd => self.on_public_end(),
+            _ => (),
+        }
+    }
+
+    fn on_str_value(&mut self, value: &[u8]) {
+        if let (State::FileStrName, Some(dedupe)) = (self.state, &mut self.dedupe) {
+            // Reported once the record is known not to be a duplicate
+            dedupe.on_str_value(value);
+            return;
+        }
+        #[cfg(feature = "func")]
+        if let (State::FuncStrName, Some(empty_funcs)) = (self.state, &mut self.empty_funcs) {
+            if empty_funcs.capturing {
+                // Reported once the address of the next symbol is known
+                empty_funcs.on_str_value(value);
+                return;
+            }
+        }
+        self.report_str_value(value);
+    }
+
+    fn report_str_value(&mut self, value: &[u8]) {
+        if let Some(string_ids) = &mut self.string_ids {
+            string_ids.push(value);
+        } else if let Some(api) = &self.api {
+            api.on_str_value(value);
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.on_str_value(value);
+        }
+        if let Some(records) = &mut self.records {
+            records.on_str_value(value);
+        }
+    }
+
     fn on_line_end(&mut self) {
-        self.api
-            .on_line(self.row[0], self.row[1], self.row[2], self.row[3]);
+        #[cfg(feature = "line")]
+        {
+            self.counters.lines += 1;
+            let [addr, size, line, mut file_index] = self.row;
+            if let Some(validator) = &mut self.validator {
+                validator.on_line(self.record_start, file_index);
+            }
+            if let Some(dedupe) = &self.dedupe {
+                file_index = dedupe.resolve(file_index);
+            }
+            if let Some(api) = &self.api {
+                api.on_line(addr, size, line, file_index);
+            }
+            if let Some(trace) = &mut self.trace {
+                trace.on_line(addr, size, line, file_index);
+            }
+            if let Some(records) = &mut self.records {
+                records.on_line(addr, size, line, file_index);
+            }
+        }
         self.on_end();
     }
 
     fn on_func_end(&mut self) {
-        self.api.on_func(self.row[0], self.row[1], self.row[2]);
+        #[cfg(feature = "func")]
+        {
+            let [addr, size, params, _] = self.row;
+            if let Some(validator) = &mut self.validator {
+                validator.on_func(self.record_start, addr, size);
+            }
+            match &mut self.empty_funcs {
+                Some(empty_funcs) if empty_funcs.capturing => empty_funcs.hold(addr, params),
+                _ => self.report_func(addr, size, params),
+            }
+        }
         self.on_end();
     }
 
+    #[cfg(feature = "func")]
+    fn report_func(&mut self, addr: u32, size: u32, params: u32) {
+        self.counters.funcs += 1;
+        self.on_str_id();
+        if let Some(api) = &self.api {
+            api.on_func(addr, size, params);
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.on_func(addr, size, params);
+        }
+        if let Some(records) = &mut self.records {
+            records.on_func(addr, size, params);
+        }
+        if let Some(shadow) = &mut self.shadow {
+            shadow.insert(addr, size);
+        }
+    }
+
+    #[cfg(feature = "func")]
+    fn flush_empty_func(&mut self, next_addr: Option<u32>) {
+        let Some(empty_funcs) = &mut self.empty_funcs else {
+            return;
+        };
+        if let Some((addr, size, params, name)) = empty_funcs.take(next_addr) {
+            self.report_str_value(&name);
+            self.report_func(addr, size, params);
+        }
+    }
+
     fn on_file_end(&mut self) {
-        self.api.on_file(self.row[0]);
+        #[cfg(feature = "file")]
+        {
+            let index = self.row[0];
+            if let Some(validator) = &mut self.validator {
+                validator.on_file(index);
+            }
+            if let Some(dedupe) = &mut self.dedupe {
+                let Some(name) = dedupe.on_file(index) else {
+                    self.on_end();
+                    return;
+                };
+                self.on_str_value(&name);
+            }
+
+            self.counters.files += 1;
+            self.on_str_id();
+            if let Some(api) = &self.api {
+                api.on_file(index);
+            }
+            if let Some(trace) = &mut self.trace {
+                trace.on_file(index);
+            }
+            if let Some(records) = &mut self.records {
+                records.on_file(index);
+            }
+        }
         self.on_end();
     }
 
     fn on_public_end(&mut self) {
-        self.api.on_public(self.row[0], self.row[1]);
+        #[cfg(feature = "public")]
+        {
+            self.counters.publics += 1;
+            let [addr, params, ..] = self.row;
+            self.on_str_id();
+            if let Some(api) = &self.api {
+                api.on_public(addr, params);
+            }
+            if let Some(trace) = &mut self.trace {
+                trace.on_public(addr, params);
+            }
+            if let Some(records) = &mut self.records {
+                records.on_public(addr, params);
+            }
+        }
+        self.on_end();
+    }
+
+    fn on_str_line_end(&mut self) {
+        if self.line.last() == Some(&b'\r') {
+            self.line.pop();
+        }
+        let line = mem::take(&mut self.line);
+        match self.state {
+            State::ModuleStrLine => {
+                self.module = line;
+                let id = self.module_field(2).unwrap_or_default();
+                if let Some(validator) = &mut self.validator {
+                    if normalize_debug_id(&id).is_none() {
+                        let kind = FindingKind::InvalidDebugId;
+                        validator.report(kind, self.record_start, 0);
+                    }
+                }
+            }
+            State::ExtensionStrLine => self.on_extension(&line),
+            State::InfoStrLine => {
+                // INFO <key> <value>
+                let (key, value) = metadata::split_field(&line);
+                match key {
+                    // INFO CODE_ID <code id> [<code file>]
+                    b"CODE_ID" => self.code_id = metadata::split_field(value).0.to_vec(),
+                    b"GENERATOR" => self.generator = value.to_vec(),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
         self.on_end();
     }
 
+    fn on_extension(&self, rest: &[u8]) {
+        if let Some(api) = &self.api {
+            api.on_extension(self.extension, rest);
+        }
+    }
+
+    #[cfg(any(feature = "func", feature = "file", feature = "public"))]
+    fn on_str_id(&mut self) {
+        if let Some(string_ids) = &mut self.string_ids {
+            let id = string_ids.finish();
+            if let Some(api) = &self.api {
+                api.on_str_id(id);
+            }
+        }
+    }
+
+    fn module_field(&self, index: usize) -> Option<String> {
+        // MODULE <os> <arch> <debug id> <name>
+        let mut rest = &self.module[..];
+        for _ in 0..index {
+            rest = metadata::split_field(rest).1;
+        }
+
+        // Name is the last field and might contain spaces
+        let field = if index == 3 {
+            rest
+        } else {
+            metadata::split_field(rest).0
+        };
+        (!field.is_empty()).then(|| String::from_utf8_lossy(field).into_owned())
+    }
+
     fn on_end(&mut self) {
+        self.in_separator = false;
+        self.keyword_len = 0;
         self.row_pos = 0;
         self.row = [0; 4];
         self.state = State::Start;
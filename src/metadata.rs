@@ -0,0 +1,52 @@
+use wasm_bindgen::prelude::*;
+
+/// Hints about the producer of a symbol file and the record types it uses.
+#[wasm_bindgen(getter_with_clone)]
+pub struct Capabilities {
+    /// Operating system from the MODULE record
+    pub os: Option<String>,
+    /// Architecture from the MODULE record
+    pub arch: Option<String>,
+    /// Value of the `INFO GENERATOR` record, if any
+    pub generator: Option<String>,
+    /// Whether an `INFO CODE_ID` record was seen
+    #[wasm_bindgen(js_name = hasCodeId)]
+    pub has_code_id: bool,
+    /// Whether any INLINE or INLINE_ORIGIN records were seen
+    #[wasm_bindgen(js_name = hasInline)]
+    pub has_inline: bool,
+    /// Whether any STACK CFI records were seen
+    #[wasm_bindgen(js_name = hasStackCfi)]
+    pub has_stack_cfi: bool,
+    /// Whether any STACK WIN records were seen
+    #[wasm_bindgen(js_name = hasStackWin)]
+    pub has_stack_win: bool,
+}
+
+/// Record types that are skipped, but whose presence is noted.
+#[derive(Default)]
+pub struct Seen {
+    pub inline: bool,
+    pub stack_cfi: bool,
+    pub stack_win: bool,
+}
+
+/// Split off the first separator-delimited field of `line`, returning it
+/// and the rest of the line with leading separators removed.
+pub fn split_field(line: &[u8]) -> (&[u8], &[u8]) {
+    let line = trim_start(line);
+    let end = line
+        .iter()
+        .position(|&ch| crate::is_separator(ch))
+        .unwrap_or(line.len());
+    let (field, rest) = line.split_at(end);
+    (field, trim_start(rest))
+}
+
+fn trim_start(line: &[u8]) -> &[u8] {
+    let start = line
+        .iter()
+        .position(|&ch| !crate::is_separator(ch))
+        .unwrap_or(line.len());
+    &line[start..]
+}
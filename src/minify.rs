@@ -0,0 +1,107 @@
+use crate::raw::{self, RecordKind};
+use memchr::{memchr, memchr3};
+use wasm_bindgen::prelude::*;
+
+/// Longest leading word that can still identify a dropped record
+const MAX_WORD: usize = 16;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Word,
+    Keep,
+    Drop,
+}
+
+/// Rewrites a .sym file keeping only what is needed for function-level
+/// symbolication and unwinding. LINE, FILE, INLINE and INLINE_ORIGIN
+/// records are dropped, everything else is copied verbatim.
+#[wasm_bindgen]
+pub struct Minifier {
+    mode: Mode,
+    word: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl Minifier {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Word,
+            word: Vec::new(),
+        }
+    }
+
+    /// Minify the next chunk of input, returning the bytes to emit for it.
+    /// Chunks can split records at arbitrary points.
+    pub fn minify(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(chunk.len());
+        let mut offset = 0;
+        while offset < chunk.len() {
+            let rest = &chunk[offset..];
+            match self.mode {
+                Mode::Word => match memchr3(b' ', b'\t', b'\n', rest) {
+                    Some(len) if self.word.len() + len <= MAX_WORD => {
+                        self.word.extend_from_slice(&rest[..len]);
+                        self.on_word_end(&mut out);
+                        offset += len;
+                    }
+                    None if self.word.len() + rest.len() <= MAX_WORD => {
+                        self.word.extend_from_slice(rest);
+                        offset = chunk.len();
+                    }
+                    _ => {
+                        // Too long to be a dropped record
+                        out.append(&mut self.word);
+                        self.mode = Mode::Keep;
+                    }
+                },
+                Mode::Keep | Mode::Drop => {
+                    let len = match memchr(b'\n', rest) {
+                        Some(i) => i + 1,
+                        None => rest.len(),
+                    };
+                    if self.mode == Mode::Keep {
+                        out.extend_from_slice(&rest[..len]);
+                    }
+                    if rest[len - 1] == b'\n' {
+                        self.mode = Mode::Word;
+                    }
+                    offset += len;
+                }
+            }
+        }
+        out
+    }
+
+    /// Returns the remaining output once the input has ended.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        if self.mode == Mode::Word {
+            self.on_word_end(&mut out);
+        }
+        self.mode = Mode::Word;
+        out
+    }
+}
+
+impl Default for Minifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Minifier {
+    fn on_word_end(&mut self, out: &mut Vec<u8>) {
+        let kind = raw::classify(&self.word);
+        if matches!(
+            kind,
+            RecordKind::File | RecordKind::Line | RecordKind::Inline | RecordKind::InlineOrigin
+        ) {
+            self.mode = Mode::Drop;
+        } else {
+            out.append(&mut self.word);
+            self.mode = Mode::Keep;
+        }
+        self.word.clear();
+    }
+}
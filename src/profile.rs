@@ -0,0 +1,54 @@
+use crate::{State, STATE_COUNT};
+
+pub struct Profile {
+    bytes: [u64; STATE_COUNT],
+    steps: [u64; STATE_COUNT],
+    /// Milliseconds spent in each state, sampled at state transitions
+    time: [f64; STATE_COUNT],
+    since: f64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            bytes: [0; STATE_COUNT],
+            steps: [0; STATE_COUNT],
+            time: [0.0; STATE_COUNT],
+            since: 0.0,
+        }
+    }
+}
+
+impl Profile {
+    /// Start the clock, time between `parse()` calls is not counted.
+    pub fn resume(&mut self) {
+        self.since = js_sys::Date::now();
+    }
+
+    /// `next` is the state after the step, the clock is only read when it
+    /// differs from `state`.
+    pub fn record(&mut self, state: State, next: State, bytes: usize) {
+        self.bytes[state as usize] += bytes as u64;
+        self.steps[state as usize] += 1;
+        if state as u8 != next as u8 {
+            self.pause(state);
+            self.resume();
+        }
+    }
+
+    pub fn pause(&mut self, state: State) {
+        self.time[state as usize] += js_sys::Date::now() - self.since;
+    }
+
+    pub fn to_vec(&self) -> Vec<u64> {
+        self.bytes
+            .iter()
+            .zip(self.steps.iter())
+            .flat_map(|(&bytes, &steps)| [bytes, steps])
+            .collect()
+    }
+
+    pub fn time(&self) -> Vec<f64> {
+        self.time.to_vec()
+    }
+}
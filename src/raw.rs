@@ -0,0 +1,94 @@
+use crate::{checkpoint, hex_value, is_separator};
+use wasm_bindgen::prelude::*;
+
+/// Record type of a line, judged by its first word.
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum RecordKind {
+    Module = 0,
+    Info = 1,
+    File = 2,
+    Func = 3,
+    Line = 4,
+    Public = 5,
+    Stack = 6,
+    Inline = 7,
+    InlineOrigin = 8,
+    /// Blank lines and unknown records
+    Other = 9,
+}
+
+pub fn classify(line: &[u8]) -> RecordKind {
+    let word = line
+        .split(|&ch| is_separator(ch) || ch == b'\r' || ch == b'\n')
+        .next()
+        .unwrap_or_default();
+    match word {
+        b"MODULE" => RecordKind::Module,
+        b"INFO" => RecordKind::Info,
+        b"FILE" => RecordKind::File,
+        b"FUNC" => RecordKind::Func,
+        b"PUBLIC" => RecordKind::Public,
+        b"STACK" => RecordKind::Stack,
+        b"INLINE" => RecordKind::Inline,
+        b"INLINE_ORIGIN" => RecordKind::InlineOrigin,
+        // LINE records start with the address, in lowercase hex like every
+        // numeric field
+        _ if !word.is_empty() && word.iter().all(|&ch| hex_value(ch).is_some()) => RecordKind::Line,
+        _ => RecordKind::Other,
+    }
+}
+
+/// Reassembles the input into lines for `onRawLine`.
+#[derive(Default)]
+pub struct RawLines {
+    /// Skip the parsing of records and only report raw lines
+    pub exclusive: bool,
+    line: Vec<u8>,
+}
+
+impl RawLines {
+    /// `bytes` must not contain a newline anywhere but at the end. Calls `f`
+    /// with the whole line, including its terminator, once it is complete.
+    pub fn feed(&mut self, bytes: &[u8], f: impl FnOnce(&[u8])) {
+        let complete = bytes.last() == Some(&b'\n');
+        if !complete {
+            self.line.extend_from_slice(bytes);
+        } else if self.line.is_empty() {
+            f(bytes);
+        } else {
+            self.line.extend_from_slice(bytes);
+            f(&self.line);
+            self.line.clear();
+        }
+    }
+
+    /// Calls `f` with the last line if the input didn't end with a newline.
+    pub fn finish(&mut self, f: impl FnOnce(&[u8])) {
+        if !self.line.is_empty() {
+            f(&self.line);
+            self.line.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.line.clear();
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.u8(self.exclusive as u8);
+        w.bytes(&self.line);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        Some(Self {
+            exclusive: match r.u8()? {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+            line: r.bytes()?.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use crate::checkpoint;
+use crate::strings::StringTable;
+
+/// Records collected in accumulate mode, packed into flat arrays. Names are
+/// referenced by id into the string table.
+#[derive(Default)]
+pub struct Records {
+    /// `[index, name id]` per FILE record
+    pub files: Vec<u32>,
+    /// `[addr, size, params, name id]` per FUNC record
+    pub funcs: Vec<u32>,
+    /// `[addr, size, line, file index]` per LINE record
+    pub lines: Vec<u32>,
+    /// `[addr, params, name id]` per PUBLIC record
+    pub publics: Vec<u32>,
+    pub strings: StringTable,
+}
+
+impl Records {
+    #[cfg(feature = "line")]
+    pub fn on_line(&mut self, addr: u32, size: u32, line: u32, file_index: u32) {
+        self.lines
+            .extend_from_slice(&[addr, size, line, file_index]);
+    }
+
+    #[cfg(feature = "func")]
+    pub fn on_func(&mut self, addr: u32, size: u32, params: u32) {
+        let name = self.strings.finish();
+        self.funcs.extend_from_slice(&[addr, size, params, name]);
+    }
+
+    #[cfg(feature = "file")]
+    pub fn on_file(&mut self, index: u32) {
+        let name = self.strings.finish();
+        self.files.extend_from_slice(&[index, name]);
+    }
+
+    #[cfg(feature = "public")]
+    pub fn on_public(&mut self, addr: u32, params: u32) {
+        let name = self.strings.finish();
+        self.publics.extend_from_slice(&[addr, params, name]);
+    }
+
+    pub fn on_str_value(&mut self, value: &[u8]) {
+        self.strings.push(value);
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+        self.funcs.clear();
+        self.lines.clear();
+        self.publics.clear();
+        self.strings.clear();
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.u32s(&self.files);
+        w.u32s(&self.funcs);
+        w.u32s(&self.lines);
+        w.u32s(&self.publics);
+        self.strings.save(w);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        Some(Self {
+            files: r.u32s()?,
+            funcs: r.u32s()?,
+            lines: r.u32s()?,
+            publics: r.u32s()?,
+            strings: StringTable::restore(r)?,
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use crate::raw::{self, RecordKind};
+use crate::{is_separator, sha256};
+use core::fmt::Write;
+use memchr::memchr;
+use wasm_bindgen::prelude::*;
+
+/// Rewrites the paths of FILE records so that the .sym file can be shared
+/// without leaking where and by whom the module was built. Everything else,
+/// FILE indices included, is copied verbatim.
+#[wasm_bindgen]
+pub struct Scrubber {
+    salt: Option<Vec<u8>>,
+    lines: raw::RawLines,
+}
+
+#[wasm_bindgen]
+impl Scrubber {
+    /// With a `salt` every path is replaced by the hex SHA-256 digest of the
+    /// salt followed by the path. Without one, only the home directory prefix
+    /// (`/home/<user>`, `/Users/<user>`, `C:\Users\<user>`) is replaced by
+    /// `~`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(salt: Option<String>) -> Self {
+        Self {
+            salt: salt.map(String::into_bytes),
+            lines: raw::RawLines::default(),
+        }
+    }
+
+    /// Scrub the next chunk of input, returning the bytes to emit for it.
+    /// Chunks can split records at arbitrary points.
+    pub fn scrub(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(chunk.len());
+        let mut offset = 0;
+        while offset < chunk.len() {
+            let end = match memchr(b'\n', &chunk[offset..]) {
+                Some(i) => offset + i + 1,
+                None => chunk.len(),
+            };
+            let salt = self.salt.as_deref();
+            self.lines
+                .feed(&chunk[offset..end], |line| scrub_line(salt, line, &mut out));
+            offset = end;
+        }
+        out
+    }
+
+    /// Returns the remaining output once the input has ended.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let salt = self.salt.as_deref();
+        self.lines.finish(|line| scrub_line(salt, line, &mut out));
+        out
+    }
+}
+
+fn scrub_line(salt: Option<&[u8]>, line: &[u8], out: &mut Vec<u8>) {
+    let body = line.strip_suffix(b"\n").unwrap_or(line);
+    let body = body.strip_suffix(b"\r").unwrap_or(body);
+
+    // FILE <index> <path>
+    let path_start = (raw::classify(line) == RecordKind::File)
+        .then(|| path_start(body))
+        .flatten();
+    let Some(path_start) = path_start else {
+        out.extend_from_slice(line);
+        return;
+    };
+
+    let path = &body[path_start..];
+    out.extend_from_slice(&body[..path_start]);
+    match salt {
+        Some(salt) => {
+            let mut hash = sha256::Sha256::default();
+            hash.update(salt);
+            hash.update(path);
+            let mut hex = String::with_capacity(64);
+            for byte in hash.finish() {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            out.extend_from_slice(hex.as_bytes());
+        }
+        None => match home_dir_len(path) {
+            Some(len) => {
+                out.push(b'~');
+                out.extend_from_slice(&path[len..]);
+            }
+            None => out.extend_from_slice(path),
+        },
+    }
+    out.extend_from_slice(&line[body.len()..]);
+}
+
+fn path_start(body: &[u8]) -> Option<usize> {
+    let keyword_len = b"FILE".len();
+    let rest = &body[keyword_len..];
+    let index_start = rest.iter().position(|&ch| !is_separator(ch))?;
+    let index_end = index_start
+        + rest[index_start..]
+            .iter()
+            .take_while(|ch| ch.is_ascii_digit())
+            .count();
+    let path_offset = rest[index_end..].iter().position(|&ch| !is_separator(ch))?;
+    if index_end == index_start || path_offset == 0 {
+        return None;
+    }
+    Some(keyword_len + index_end + path_offset)
+}
+
+fn home_dir_len(path: &[u8]) -> Option<usize> {
+    let is_path_separator = |ch: u8| ch == b'/' || ch == b'\\';
+
+    // C:\Users\ or C:/Users/
+    let is_windows = path.len() > 9
+        && path[0].is_ascii_alphabetic()
+        && path[1] == b':'
+        && is_path_separator(path[2])
+        && path[3..8].eq_ignore_ascii_case(b"users")
+        && is_path_separator(path[8]);
+
+    let prefix_len = if path.starts_with(b"/home/") {
+        6
+    } else if path.starts_with(b"/Users/") {
+        7
+    } else if is_windows {
+        9
+    } else {
+        return None;
+    };
+
+    // User name must be followed by more of the path
+    let user_len = path[prefix_len..]
+        .iter()
+        .position(|&ch| is_path_separator(ch))?;
+    (user_len > 0).then_some(prefix_len + user_len)
+}
@@ -0,0 +1,97 @@
+use crate::checkpoint;
+
+/// Address ranges of the FUNC records seen so far, used to drop PUBLIC
+/// records that they already cover.
+pub struct FuncRanges {
+    /// `(start, end)` pairs, non-overlapping and sorted unless `sorted` is
+    /// false
+    ranges: Vec<(u32, u32)>,
+    sorted: bool,
+}
+
+impl Default for FuncRanges {
+    fn default() -> Self {
+        Self {
+            ranges: Vec::new(),
+            sorted: true,
+        }
+    }
+}
+
+impl FuncRanges {
+    #[cfg(feature = "func")]
+    pub fn insert(&mut self, addr: u32, size: u32) {
+        if size == 0 {
+            return;
+        }
+        let end = addr.saturating_add(size);
+
+        // FUNC records are usually sorted, merge them as they come
+        if let Some(last) = self.ranges.last_mut() {
+            if self.sorted && addr >= last.0 {
+                if addr <= last.1 {
+                    last.1 = last.1.max(end);
+                    return;
+                }
+            } else {
+                self.sorted = false;
+            }
+        }
+        self.ranges.push((addr, end));
+    }
+
+    #[cfg(feature = "public")]
+    pub fn contains(&mut self, addr: u32) -> bool {
+        if !self.sorted {
+            self.normalize();
+        }
+        let i = self.ranges.partition_point(|&(start, _)| start <= addr);
+        i != 0 && addr < self.ranges[i - 1].1
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.sorted = true;
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        let flat: Vec<u32> = self
+            .ranges
+            .iter()
+            .flat_map(|&(start, end)| [start, end])
+            .collect();
+        w.u32s(&flat);
+        w.u8(self.sorted as u8);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let flat = r.u32s()?;
+        if flat.len() % 2 != 0 {
+            return None;
+        }
+        let sorted = match r.u8()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        Some(Self {
+            ranges: flat.chunks_exact(2).map(|p| (p[0], p[1])).collect(),
+            sorted,
+        })
+    }
+
+    #[cfg(feature = "public")]
+    fn normalize(&mut self) {
+        self.ranges.sort_unstable();
+
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.ranges.len());
+        for &(start, end) in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        self.ranges = merged;
+        self.sorted = true;
+    }
+}
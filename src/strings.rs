@@ -0,0 +1,63 @@
+use crate::checkpoint;
+
+/// Names packed into a single buffer: string `id` spans
+/// `bytes[offsets[id]..offsets[id + 1]]`. Bytes past the last offset belong
+/// to the string that is still being received.
+pub struct StringTable {
+    pub bytes: Vec<u8>,
+    pub offsets: Vec<u32>,
+}
+
+impl Default for StringTable {
+    fn default() -> Self {
+        Self {
+            bytes: Vec::new(),
+            offsets: vec![0],
+        }
+    }
+}
+
+impl StringTable {
+    pub fn push(&mut self, value: &[u8]) {
+        self.bytes.extend_from_slice(value);
+    }
+
+    /// Completes the current string and returns its id.
+    #[cfg(any(feature = "func", feature = "file", feature = "public"))]
+    pub fn finish(&mut self) -> u32 {
+        let id = self.offsets.len() as u32 - 1;
+        self.offsets.push(self.bytes.len() as u32);
+        id
+    }
+
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        let id = id as usize;
+        let start = *self.offsets.get(id)? as usize;
+        let end = *self.offsets.get(id + 1)? as usize;
+        Some(&self.bytes[start..end])
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+        self.offsets.truncate(1);
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.bytes(&self.bytes);
+        w.u32s(&self.offsets);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let table = Self {
+            bytes: r.bytes()?.to_vec(),
+            offsets: r.u32s()?,
+        };
+        if table.offsets.first() != Some(&0)
+            || table.offsets.windows(2).any(|pair| pair[0] > pair[1])
+            || *table.offsets.last()? as usize > table.bytes.len()
+        {
+            return None;
+        }
+        Some(table)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{hex_value, is_separator};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    pub type TokenApi;
+
+    #[wasm_bindgen(method, js_name = onToken)]
+    fn on_token(api: &TokenApi, kind: TokenKind, bytes: &[u8]);
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum TokenKind {
+    /// Uppercase word at the start of a line
+    Keyword = 0,
+    /// Word made of decimal digits and lowercase hex letters, the characters
+    /// accepted in numeric fields. Whether it is meant as hex or decimal
+    /// depends on the field it is in.
+    Number = 1,
+    /// Any other word
+    String = 2,
+    /// Run of spaces and tabs between words
+    Separator = 3,
+    /// Reported with empty bytes at the end of every line
+    EndOfLine = 4,
+}
+
+/// Splits the input into words and separator runs without interpreting
+/// records, for consumers that implement their own record handling.
+/// Tokens are lexical only: a name containing spaces is reported as several
+/// words, which the consumer can join back together with the separators
+/// between them.
+#[wasm_bindgen]
+pub struct Tokenizer {
+    api: TokenApi,
+    token: Vec<u8>,
+    /// Whether `token` holds separators rather than a word
+    in_separator: bool,
+    at_line_start: bool,
+}
+
+#[wasm_bindgen]
+impl Tokenizer {
+    #[wasm_bindgen(constructor)]
+    pub fn new(api: TokenApi) -> Self {
+        Self {
+            api,
+            token: Vec::new(),
+            in_separator: false,
+            at_line_start: true,
+        }
+    }
+
+    /// Tokenize the next chunk of input. Chunks can split tokens at
+    /// arbitrary points.
+    pub fn tokenize(&mut self, chunk: &[u8]) {
+        let mut offset = 0;
+        while offset < chunk.len() {
+            if chunk[offset] == b'\n' {
+                self.flush();
+                self.on_line_end();
+                offset += 1;
+                continue;
+            }
+
+            let in_separator = is_separator(chunk[offset]);
+            if in_separator != self.in_separator {
+                self.flush();
+                self.in_separator = in_separator;
+            }
+            let Some(len) = chunk[offset..]
+                .iter()
+                .position(|&ch| ch == b'\n' || is_separator(ch) != in_separator)
+            else {
+                self.token.extend_from_slice(&chunk[offset..]);
+                return;
+            };
+            let end = offset + len;
+
+            if self.token.is_empty() {
+                self.on_token(&chunk[offset..end]);
+            } else {
+                self.token.extend_from_slice(&chunk[offset..end]);
+                self.flush();
+            }
+            offset = end;
+        }
+    }
+
+    /// Reports the last token and line once the input has ended.
+    pub fn finish(&mut self) {
+        self.flush();
+        if !self.at_line_start {
+            self.on_line_end();
+        }
+    }
+}
+
+impl Tokenizer {
+    fn on_line_end(&mut self) {
+        self.at_line_start = true;
+        self.api.on_token(TokenKind::EndOfLine, &[]);
+    }
+
+    fn flush(&mut self) {
+        let token = core::mem::take(&mut self.token);
+        self.on_token(&token);
+        self.token = token;
+        self.token.clear();
+    }
+
+    fn on_token(&mut self, token: &[u8]) {
+        let token = token.strip_suffix(b"\r").unwrap_or(token);
+        if token.is_empty() {
+            return;
+        }
+
+        let is_keyword = self.at_line_start
+            && token[0].is_ascii_uppercase()
+            && token
+                .iter()
+                .all(|&ch| matches!(ch, b'A'..=b'Z' | b'0'..=b'9' | b'_'));
+        let kind = if self.in_separator {
+            TokenKind::Separator
+        } else if is_keyword {
+            TokenKind::Keyword
+        } else if token.iter().all(|&ch| hex_value(ch).is_some()) {
+            TokenKind::Number
+        } else {
+            TokenKind::String
+        };
+
+        self.at_line_start = false;
+        self.api.on_token(kind, token);
+    }
+}
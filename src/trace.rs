@@ -0,0 +1,74 @@
+#[cfg(any(
+    feature = "file",
+    feature = "func",
+    feature = "line",
+    feature = "public"
+))]
+use core::fmt::Write;
+use core::mem;
+
+use crate::checkpoint;
+
+/// Canonical textual log of every reported record, one per line.
+#[derive(Default)]
+pub struct Trace {
+    out: String,
+    name: Vec<u8>,
+}
+
+impl Trace {
+    #[cfg(feature = "line")]
+    pub fn on_line(&mut self, addr: u32, size: u32, line: u32, file_index: u32) {
+        let _ = writeln!(self.out, "line {addr:x} {size:x} {line} {file_index}");
+    }
+
+    #[cfg(feature = "func")]
+    pub fn on_func(&mut self, addr: u32, size: u32, params: u32) {
+        let _ = write!(self.out, "func {addr:x} {size:x} {params:x} ");
+        self.push_name();
+    }
+
+    #[cfg(feature = "file")]
+    pub fn on_file(&mut self, index: u32) {
+        let _ = write!(self.out, "file {index} ");
+        self.push_name();
+    }
+
+    #[cfg(feature = "public")]
+    pub fn on_public(&mut self, addr: u32, params: u32) {
+        let _ = write!(self.out, "public {addr:x} {params:x} ");
+        self.push_name();
+    }
+
+    pub fn on_str_value(&mut self, value: &[u8]) {
+        self.name.extend_from_slice(value);
+    }
+
+    pub fn take(&mut self) -> String {
+        mem::take(&mut self.out)
+    }
+
+    pub fn clear(&mut self) {
+        self.out.clear();
+        self.name.clear();
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.bytes(self.out.as_bytes());
+        w.bytes(&self.name);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        Some(Self {
+            out: String::from_utf8(r.bytes()?.to_vec()).ok()?,
+            name: r.bytes()?.to_vec(),
+        })
+    }
+
+    #[cfg(any(feature = "func", feature = "file", feature = "public"))]
+    fn push_name(&mut self) {
+        self.out.push_str(&String::from_utf8_lossy(&self.name));
+        self.out.push('\n');
+        self.name.clear();
+    }
+}
@@ -0,0 +1,75 @@
+use crate::checkpoint;
+use core::{mem, str};
+
+const REPLACEMENT: &[u8] = "\u{fffd}".as_bytes();
+
+/// Replaces invalid UTF-8 in names with U+FFFD, the same way
+/// `String::from_utf8_lossy` does. Sequences split between pieces of a name
+/// are held back until the next piece arrives.
+#[derive(Default)]
+pub struct Utf8Repair {
+    pending: Vec<u8>,
+    out: Vec<u8>,
+    repaired: bool,
+}
+
+impl Utf8Repair {
+    /// Returns the repaired `value`, `is_end` marks the last piece of a name.
+    pub fn feed<'a>(&'a mut self, value: &'a [u8], is_end: bool) -> &'a [u8] {
+        if self.pending.is_empty() && str::from_utf8(value).is_ok() {
+            return value;
+        }
+
+        let mut input = mem::take(&mut self.pending);
+        input.extend_from_slice(value);
+        self.out.clear();
+
+        let mut rest = &input[..];
+        while let Err(err) = str::from_utf8(rest) {
+            let (valid, invalid) = rest.split_at(err.valid_up_to());
+            self.out.extend_from_slice(valid);
+            match err.error_len() {
+                Some(len) => rest = &invalid[len..],
+                None if is_end => rest = &[],
+                None => {
+                    // Incomplete sequence, might continue in the next piece
+                    self.pending.extend_from_slice(invalid);
+                    return &self.out;
+                }
+            }
+            self.out.extend_from_slice(REPLACEMENT);
+            self.repaired = true;
+        }
+        self.out.extend_from_slice(rest);
+        &self.out
+    }
+
+    /// Returns whether the name that just ended needed a repair.
+    pub fn take_repaired(&mut self) -> bool {
+        mem::take(&mut self.repaired)
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.repaired = false;
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.bytes(&self.pending);
+        w.u8(self.repaired as u8);
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let pending = r.bytes()?.to_vec();
+        let repaired = match r.u8()? {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        Some(Self {
+            pending,
+            out: Vec::new(),
+            repaired,
+        })
+    }
+}
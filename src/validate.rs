@@ -0,0 +1,163 @@
+use crate::checkpoint;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum FindingKind {
+    /// No MODULE record in the file
+    MissingModule = 0,
+    /// MODULE record with a debug id that `normalizeDebugId()` rejects
+    InvalidDebugId = 1,
+    /// LINE record referencing a FILE index that is never defined, the
+    /// index is in `value`
+    UndefinedFile = 2,
+    /// FUNC record with size 0, the address is in `value`
+    EmptyFunc = 3,
+    /// FUNC record whose end does not fit in 32 bits, the address is in
+    /// `value`
+    FuncOverflow = 4,
+    /// Record with missing or extra fields that was dropped
+    MalformedRecord = 5,
+    /// Input ended in the middle of a record
+    Truncated = 6,
+}
+
+impl FindingKind {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::MissingModule,
+            1 => Self::InvalidDebugId,
+            2 => Self::UndefinedFile,
+            3 => Self::EmptyFunc,
+            4 => Self::FuncOverflow,
+            5 => Self::MalformedRecord,
+            6 => Self::Truncated,
+            _ => return None,
+        })
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// Byte offset of the start of the offending record
+    pub offset: f64,
+    /// Extra information, depending on `kind`
+    pub value: u32,
+}
+
+#[derive(Default)]
+pub struct Validator {
+    findings: Vec<(FindingKind, u64, u32)>,
+    files: HashSet<u32>,
+    /// First offset at which each not yet defined FILE index was referenced
+    undefined: HashMap<u32, u64>,
+}
+
+impl Validator {
+    pub fn report(&mut self, kind: FindingKind, offset: u64, value: u32) {
+        self.findings.push((kind, offset, value));
+    }
+
+    #[cfg(feature = "file")]
+    pub fn on_file(&mut self, index: u32) {
+        self.files.insert(index);
+    }
+
+    #[cfg(feature = "func")]
+    pub fn on_func(&mut self, offset: u64, addr: u32, size: u32) {
+        if size == 0 {
+            self.report(FindingKind::EmptyFunc, offset, addr);
+        } else if addr.checked_add(size).is_none() {
+            self.report(FindingKind::FuncOverflow, offset, addr);
+        }
+    }
+
+    #[cfg(feature = "line")]
+    pub fn on_line(&mut self, offset: u64, file_index: u32) {
+        if !self.files.contains(&file_index) {
+            self.undefined.entry(file_index).or_insert(offset);
+        }
+    }
+
+    pub fn finish(&mut self, has_module: bool) {
+        if !has_module {
+            self.report(FindingKind::MissingModule, 0, 0);
+        }
+
+        let mut undefined: Vec<_> = self
+            .undefined
+            .drain()
+            .filter(|(index, _)| !self.files.contains(index))
+            .collect();
+        undefined.sort_unstable_by_key(|&(_, offset)| offset);
+        for (index, offset) in undefined {
+            self.report(FindingKind::UndefinedFile, offset, index);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.findings.clear();
+        self.files.clear();
+        self.undefined.clear();
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        self.findings
+            .iter()
+            .map(|&(kind, offset, value)| Finding {
+                kind,
+                offset: offset as f64,
+                value,
+            })
+            .collect()
+    }
+
+    pub fn save(&self, w: &mut checkpoint::Writer) {
+        w.u32(self.findings.len() as u32);
+        for &(kind, offset, value) in &self.findings {
+            w.u8(kind as u8);
+            w.u64(offset);
+            w.u32(value);
+        }
+
+        let mut files: Vec<u32> = self.files.iter().copied().collect();
+        files.sort_unstable();
+        w.u32s(&files);
+
+        let mut undefined: Vec<_> = self.undefined.iter().collect();
+        undefined.sort_unstable();
+        w.u32(undefined.len() as u32);
+        for (&index, &offset) in undefined {
+            w.u32(index);
+            w.u64(offset);
+        }
+    }
+
+    pub fn restore(r: &mut checkpoint::Reader) -> Option<Self> {
+        let len = r.u32()?;
+        let mut findings = Vec::new();
+        for _ in 0..len {
+            let kind = FindingKind::from_u8(r.u8()?)?;
+            findings.push((kind, r.u64()?, r.u32()?));
+        }
+
+        let files = r.u32s()?.into_iter().collect();
+
+        let len = r.u32()?;
+        let mut undefined = HashMap::new();
+        for _ in 0..len {
+            let index = r.u32()?;
+            undefined.insert(index, r.u64()?);
+        }
+
+        Some(Self {
+            findings,
+            files,
+            undefined,
+        })
+    }
+}
@@ -1,50 +1,76 @@
+import { createHash } from 'node:crypto';
 import test from 'ava';
 
-import { Parser } from '../pkg/breakpad_parser_wasm.js';
+import {
+  Parser,
+  normalizeDebugId,
+  EmptyFuncPolicy,
+  FindingKind,
+  Minifier,
+  RecordKind,
+  Scrubber,
+  TokenKind,
+  Tokenizer,
+} from '../pkg/breakpad_parser_wasm.js';
 
 const DECODER = new TextDecoder();
 const ENCODER = new TextEncoder();
 
-async function parse(chunks) {
+const NOOP_API = {
+  onLine() {},
+  onFile() {},
+  onFunc() {},
+  onPublic() {},
+  onExtension() {},
+  onNameRepaired() {},
+  onRawLine() {},
+  onStrValue() {},
+  onStrId() {},
+};
+
+// Collects every reported record as `[type, ...args, name]`. `callbacks`
+// receives the result array and returns extra or overriding callbacks.
+function collect(callbacks = () => ({})) {
   const result = [];
 
   let strBuffer = '';
+  const named = type => (...args) => {
+    result.push([type, ...args, strBuffer]);
+    strBuffer = '';
+  };
 
-  const p = new Parser({
+  const api = {
+    ...NOOP_API,
     onLine: (...args) => result.push(['line', ...args]),
-    onFile: (...args) => {
-      result.push(['file', ...args, strBuffer]);
-      strBuffer = '';
-    },
-    onFunc: (...args) => {
-      result.push(['func', ...args, strBuffer]);
-      strBuffer = '';
-    },
-    onPublic: (...args) => {
-      result.push(['public', ...args, strBuffer]);
-      strBuffer = '';
-    },
+    onFile: named('file'),
+    onFunc: named('func'),
+    onPublic: named('public'),
     onStrValue: value => strBuffer += DECODER.decode(value),
-  });
+    ...callbacks(result),
+  };
+  return { api, result };
+}
+
+const RAW_LINE_CALLBACKS = result => ({
+  onRawLine: (kind, bytes) => result.push([kind, DECODER.decode(bytes)]),
+});
+
+// `before` configures the parser, `after` inspects it once finished.
+async function parse(chunks, { callbacks, before, after } = {}) {
+  const { api, result } = collect(callbacks);
+  const p = new Parser(api);
+  before?.(p);
 
   for (const chunk of chunks) {
     p.parse(ENCODER.encode(chunk));
   }
   p.finish();
+  after?.(p);
   p.free();
 
   return result;
 }
 
-test('it emits the trailing row', async (t) => {
-  const result = await parse([
-    'MODULE mac arm64 4C4C44C455553144A1311334F13F98720 Electron Framework\n',
-    'FILE 0 abc\n',
-  ]);
-
-  t.deepEqual(result, [['file', 0, 'abc']]);
-});
-
 test('it supports all row types', async (t) => {
   const result = await parse([
     'MODULE mac arm64 4C4C44C455553144A1311334F13F98720 Electron Framework\n',
@@ -96,3 +122,692 @@ test('it keeps windows line ends', async (t) => {
     ['line', 0x22226c, 0x20, 1874, 75507],
   ]);
 });
+
+test('it skips malformed records', async (t) => {
+  const result = await parse([
+    'FILE 0 a\r\n',
+    '22226c 20 1874 75507\r\n',
+    '22228c 4 1262 75507\n',
+    '\n',
+    '1 2 3 4 5\n',
+    '1 2\n',
+    'FILE 3\n',
+    'FUNC 1 2\n',
+    'FILE a x\n',
+    'FUNC 1z 2 3 f\n',
+    'FILE\n',
+    'FUNC 1 2 0 f\n',
+    'PUBLIC\r\n',
+    '10 4 1 0\n',
+    '222290 4 1087 75507\n',
+  ]);
+
+  t.deepEqual(result, [
+    ['file', 0, 'a\r'],
+    ['line', 0x22226c, 0x20, 1874, 75507],
+    ['line', 0x22228c, 0x4, 1262, 75507],
+    ['func', 0x1, 0x2, 0x0, 'f'],
+    ['line', 0x10, 0x4, 1, 0],
+    ['line', 0x222290, 0x4, 1087, 75507],
+  ]);
+});
+
+test('it rejects numeric fields wider than 32 bits', async (t) => {
+  const result = await parse([
+    'FUNC 123456789abcdef 2 0 f\n',
+    'FUNC ffffffff 2 0 g\n',
+    'FUNC 1ffff',
+    'ffff 1 0 h\n',
+    '1 2 4294967295 0\n',
+    '1 2 4294967296 0\n',
+  ]);
+
+  t.deepEqual(result, [
+    ['func', 0xffffffff, 2, 0, 'g'],
+    ['line', 1, 2, 4294967295, 0],
+  ]);
+});
+
+test('it exposes module architecture', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.parse(ENCODER.encode('MODULE mac ar'));
+  p.parse(ENCODER.encode('m64 4C4C44C455553144A1311334F13F98720 Electron\n'));
+  p.parse(ENCODER.encode('FILE 0 a\n'));
+  p.finish();
+
+  t.is(p.moduleArch(), 'arm64');
+  p.free();
+});
+
+test('it exposes module metadata', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.parse(ENCODER.encode([
+    'MODULE mac arm64 4C4C44C455553144A1311334F13F98720 Electron Framework\n',
+    'INFO CODE_ID 4C4C44C455553144A1311334F13F9872\n',
+    'FILE 0 a\n',
+  ].join('')));
+  p.finish();
+
+  t.is(p.moduleOs(), 'mac');
+  t.is(p.debugId(), '4C4C44C455553144A1311334F13F98720');
+  t.is(p.moduleName(), 'Electron Framework');
+  t.is(p.codeId(), '4C4C44C455553144A1311334F13F9872');
+  p.free();
+});
+
+test('it exposes a code id followed by a code file', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.parse(ENCODER.encode([
+    'MODULE windows x86_64 5F4A3E2B1C0D4E5F8A9B0C1D2E3F40511 chrome.dll.pdb\n',
+    'INFO CODE_ID 5C8B2B8A1000 chrome.dll\r\n',
+  ].join('')));
+  p.finish();
+
+  t.is(p.codeId(), '5C8B2B8A1000');
+  p.free();
+});
+
+test('it resumes from a saved state', async (t) => {
+  const { api, result } = collect();
+
+  const first = new Parser(api);
+  first.enableTrace();
+  first.parse(ENCODER.encode('MODULE mac arm64 4C4C44C455553144A1311334F13F98720 E'));
+  first.parse(ENCODER.encode('\nFUNC 22226c 12c 0 bool my_'));
+  const state = first.saveState();
+  first.free();
+
+  const second = new Parser(api);
+  second.restoreState(state);
+  second.parse(ENCODER.encode('func()\n2222'));
+  second.parse(ENCODER.encode('6c 20 1874 75507\n'));
+  second.finish();
+
+  t.is(second.moduleArch(), 'arm64');
+  t.deepEqual(result, [
+    ['func', 0x22226c, 0x12c, 0x0, 'bool my_func()'],
+    ['line', 0x22226c, 0x20, 1874, 75507],
+  ]);
+  t.is(second.takeTrace(), [
+    'func 22226c 12c 0 bool my_func()',
+    'line 22226c 20 1874 75507',
+    '',
+  ].join('\n'));
+  t.throws(() => second.restoreState(new Uint8Array([0xff])), {
+    message: 'Unsupported parser state version',
+  });
+  t.throws(() => second.restoreState(state.subarray(0, 10)), {
+    message: 'Invalid parser state',
+  });
+  second.free();
+});
+
+test('it tolerates runs of separators between fields', async (t) => {
+  const result = await parse([
+    'FILE  0 \ta\n',
+    'FUNC 22226c   12c\t0  bool my_func()\n',
+    '22226c \t20  ',
+    '  1874 \t75507\n',
+    '1 2 3 \n',
+    '1 2 3\t\t\n',
+    'FUNC 1  \n',
+    '22226c 20 1874 7 \n',
+    '22226c 20 1874 7\t\r\n',
+    '1 2 3 4 x\n',
+    'PUBLIC 2160 \t 0   Public1\n',
+  ]);
+
+  t.deepEqual(result, [
+    ['file', 0, 'a'],
+    ['func', 0x22226c, 0x12c, 0x0, 'bool my_func()'],
+    ['line', 0x22226c, 0x20, 1874, 75507],
+    ['line', 0x22226c, 0x20, 1874, 7],
+    ['line', 0x22226c, 0x20, 1874, 7],
+    ['public', 0x2160, 0, 'Public1'],
+  ]);
+});
+
+test('it records a trace without callbacks', async (t) => {
+  const input = [
+    'FILE 0 a\n',
+    'FUNC 22226c 12c 0 bool my_func()\n',
+    '22226c 20 1874 75507\n',
+    'PUBLIC m 2160 0 Public1\n',
+  ].join('');
+
+  const trace = (chunkSize) => {
+    const p = new Parser();
+    p.enableTrace();
+    const bytes = ENCODER.encode(input);
+    for (let i = 0; i < bytes.length; i += chunkSize) {
+      p.parse(bytes.subarray(i, i + chunkSize));
+    }
+    p.finish();
+    const result = p.takeTrace();
+    p.free();
+    return result;
+  };
+
+  t.is(trace(1024), [
+    'file 0 a',
+    'func 22226c 12c 0 bool my_func()',
+    'line 22226c 20 1874 75507',
+    'public 2160 0 Public1',
+    '',
+  ].join('\n'));
+  t.is(trace(1), trace(1024));
+  t.is(trace(7), trace(1024));
+});
+
+test('it verifies the sha256 digest of the input', async (t) => {
+  const input = ENCODER.encode('FILE 0 a\nFUNC 1 2 3 x\n');
+  const digest = createHash('sha256').update(input).digest('hex');
+
+  const good = new Parser(NOOP_API);
+  good.verifySha256(digest);
+  good.parse(input.subarray(0, 5));
+  good.parse(input.subarray(5));
+  t.notThrows(() => good.finish());
+  good.free();
+
+  const bad = new Parser(NOOP_API);
+  bad.verifySha256(digest);
+  bad.parse(input.subarray(1));
+  t.throws(() => bad.finish(), { message: 'SHA-256 digest mismatch' });
+  bad.free();
+
+  t.throws(() => new Parser().verifySha256('abc'));
+});
+
+test('it accumulates records into packed arrays', async (t) => {
+  const p = new Parser();
+  p.enableAccumulate();
+  p.parse(ENCODER.encode('FILE 0 a\nFILE 1 b'));
+  p.parse(ENCODER.encode('c\nFUNC 22226c 12c 0 bool my_func()\n'));
+  p.parse(ENCODER.encode('22226c 20 1874 1\nPUBLIC m 2160 0 Public1\n'));
+  p.finish();
+
+  const strings = p.getStrings();
+  const offsets = p.getStringOffsets();
+  const name = (id) =>
+    DECODER.decode(strings.subarray(offsets[id], offsets[id + 1]));
+
+  t.deepEqual(Array.from(p.getFiles()), [0, 0, 1, 1]);
+  t.deepEqual(Array.from(p.getFuncs()), [0x22226c, 0x12c, 0, 2]);
+  t.deepEqual(Array.from(p.getLines()), [0x22226c, 0x20, 1874, 1]);
+  t.deepEqual(Array.from(p.getPublics()), [0x2160, 0, 3]);
+  t.deepEqual([0, 1, 2, 3].map(name), ['a', 'bc', 'bool my_func()', 'Public1']);
+  p.free();
+});
+
+test('it counts records while parsing', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.parse(ENCODER.encode('FILE 0 a\nFILE 1 b\nFUNC 1 2 3 x\n'));
+  t.is(p.fileCount(), 2);
+  t.is(p.funcCount(), 1);
+  t.is(p.lineCount(), 0);
+
+  p.parse(ENCODER.encode('1 2 3 0\n1 2 4 0\nPUBLIC 10 0 y\n'));
+  t.is(p.lineCount(), 2);
+  t.is(p.publicCount(), 1);
+  t.is(p.bytesParsed(), 61);
+  p.free();
+});
+
+test('it deduplicates FILE records with identical names', async (t) => {
+  const result = await parse([
+    'FILE 0 a/b\nFILE 1 c\nFILE 2 a',
+    '/b\nFILE 3 c\n1 2 3 0\n1 2 3 2\n1 2 3 3\n',
+  ], {
+    before: p => p.dedupeFiles(),
+    after: p => t.deepEqual(Array.from(p.fileMapping()), [2, 0, 3, 1]),
+  });
+
+  t.deepEqual(result, [
+    ['file', 0, 'a/b'],
+    ['file', 1, 'c'],
+    ['line', 1, 2, 3, 0],
+    ['line', 1, 2, 3, 0],
+    ['line', 1, 2, 3, 1],
+  ]);
+});
+
+test('it normalizes debug ids', async (t) => {
+  const canonical = '4C4C44C455553144A1311334F13F98720';
+  t.is(normalizeDebugId('4c4c44c455553144a1311334f13f98720'), canonical);
+  t.is(normalizeDebugId('4c4c44c455553144a1311334f13f9872'), canonical);
+  t.is(normalizeDebugId('4c4c44c4-5555-3144-a131-1334f13f9872'), canonical);
+  t.is(normalizeDebugId('{4C4C44C4-5555-3144-A131-1334F13F9872}'), canonical);
+  t.is(
+    normalizeDebugId('4c4c44c4-5555-3144-a131-1334f13f9872-1a'),
+    '4C4C44C455553144A1311334F13F98721A',
+  );
+  t.is(
+    normalizeDebugId('4C4C44C455553144A1311334F13F98720000000A'),
+    '4C4C44C455553144A1311334F13F9872A',
+  );
+  t.is(normalizeDebugId('4c4c-44c4'), undefined);
+  t.is(normalizeDebugId('4C4C44C455553144A1311334F13F9872G'), undefined);
+});
+
+test('it reports producer capabilities', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.parse(ENCODER.encode([
+    'MODULE Linux x86_64 4C4C44C455553144A1311334F13F98720 libfoo.so\n',
+    'INFO CODE_ID C4444C4C55553144A1311334F13F9872 libfoo.so\n',
+    'INFO GENERATOR mozilla/dump_syms 2.3.1\n',
+    'FILE 0 a\n',
+    'INLINE_ORIGIN 0 foo\n',
+    'FUNC 1 2 3 f\n',
+    'INLINE 0 1 0 1 2\n',
+    'STACK CFI INIT 1 2 .cfa: $rsp 8 +\n',
+  ].join('')));
+  p.finish();
+
+  const caps = p.capabilities();
+  t.is(caps.os, 'Linux');
+  t.is(caps.arch, 'x86_64');
+  t.is(caps.generator, 'mozilla/dump_syms 2.3.1');
+  t.true(caps.hasCodeId);
+  t.true(caps.hasInline);
+  t.true(caps.hasStackCfi);
+  t.false(caps.hasStackWin);
+  caps.free();
+  p.free();
+});
+
+test('it drops PUBLIC records covered by FUNC records', async (t) => {
+  const p = new Parser();
+  p.enableTrace();
+  p.dropShadowedPublics();
+  p.parse(ENCODER.encode([
+    'FUNC 10 10 0 a\n',
+    'FUNC 18 10 0 b\n',
+    'PUBLIC 5 0 p5\n',
+    'PUBLIC 1f 0 p1f\n',
+    'PUBLIC 28 0 p28\n',
+  ].join('')));
+  p.finish();
+
+  t.is(p.takeTrace(), [
+    'func 10 10 0 a',
+    'func 18 10 0 b',
+    'public 5 0 p5',
+    'public 28 0 p28',
+    '',
+  ].join('\n'));
+  t.is(p.publicCount(), 2);
+  p.free();
+});
+
+test('it applies a policy to zero-size FUNC records', async (t) => {
+  const input = [
+    'FUNC 10 0 0 a\n',
+    '10 0 1 0\n',
+    'FUNC 18 8 0 b\n',
+    'FUNC m 20 0 0 c\n',
+    'PUBLIC 28 0 p\n',
+    'FUNC 30 0 0 d\n',
+  ].join('');
+
+  const trace = (policy) => {
+    const p = new Parser();
+    p.enableTrace();
+    p.setEmptyFuncPolicy(policy);
+    p.parse(ENCODER.encode(input));
+    p.finish();
+    const result = [p.takeTrace(), p.funcCount()];
+    p.free();
+    return result;
+  };
+
+  t.deepEqual(trace(EmptyFuncPolicy.Keep), [[
+    'func 10 0 0 a',
+    'line 10 0 1 0',
+    'func 18 8 0 b',
+    'func 20 0 0 c',
+    'public 28 0 p',
+    'func 30 0 0 d',
+    '',
+  ].join('\n'), 4]);
+  t.deepEqual(trace(EmptyFuncPolicy.Drop), [[
+    'line 10 0 1 0',
+    'func 18 8 0 b',
+    'public 28 0 p',
+    '',
+  ].join('\n'), 1]);
+  t.deepEqual(trace(EmptyFuncPolicy.Extend), [[
+    'line 10 0 1 0',
+    'func 10 8 0 a',
+    'func 18 8 0 b',
+    'func 20 8 0 c',
+    'public 28 0 p',
+    'func 30 0 0 d',
+    '',
+  ].join('\n'), 4]);
+});
+
+test('it parses an array of chunks in one call', async (t) => {
+  const p = new Parser();
+  p.enableTrace();
+  p.parseVectored([
+    'FILE 0 a\nFU',
+    'NC 22226c 12c 0 bool my_func()\n2222',
+    '6c 20 1874 0\n',
+  ].map(chunk => ENCODER.encode(chunk)));
+  p.finish();
+
+  t.is(p.takeTrace(), [
+    'file 0 a',
+    'func 22226c 12c 0 bool my_func()',
+    'line 22226c 20 1874 0',
+    '',
+  ].join('\n'));
+  p.free();
+});
+
+test('it reports structural problems', async (t) => {
+  const p = new Parser();
+  p.enableValidation();
+  p.parse(ENCODER.encode([
+    'MODULE Linux x86_64 ZZZ a.out\n',
+    'FILE 0 a\n',
+    'FUNC 10 0 0 f\n',
+    '10 4 1 0\n',
+    '14 4 2 3\n',
+    'FUNC ffffffff 2 0 g\n',
+    '18 4 5\n',
+    'FUNC 1 2',
+  ].join('')));
+  p.finish();
+
+  const report = p.validationReport().map(({ kind, offset, value }) => {
+    return { kind, offset, value };
+  });
+  t.deepEqual(report, [
+    { kind: FindingKind.InvalidDebugId, offset: 0, value: 0 },
+    { kind: FindingKind.EmptyFunc, offset: 39, value: 0x10 },
+    { kind: FindingKind.FuncOverflow, offset: 71, value: 0xffffffff },
+    { kind: FindingKind.MalformedRecord, offset: 91, value: 0 },
+    { kind: FindingKind.Truncated, offset: 98, value: 0 },
+    { kind: FindingKind.UndefinedFile, offset: 62, value: 3 },
+  ]);
+  p.free();
+});
+
+test('it strips line and inline data', async (t) => {
+  const m = new Minifier();
+  const out = [
+    'MODULE Linux x86_64 ID a\nFI',
+    'LE 0 a.c\nFUNC 10 4 0 f\n1',
+    '0 4 1 0\nINLINE_ORIGIN 0 g\nINLINE 0 1 0 0 10 4\n',
+    'PUBLIC 20 0 p\nSTACK CFI INIT 10 4 .cfa: $rsp 8 +\n10 4 2 0',
+  ].map(chunk => DECODER.decode(m.minify(ENCODER.encode(chunk))));
+  out.push(DECODER.decode(m.finish()));
+
+  t.is(out.join(''), [
+    'MODULE Linux x86_64 ID a',
+    'FUNC 10 4 0 f',
+    'PUBLIC 20 0 p',
+    'STACK CFI INIT 10 4 .cfa: $rsp 8 +',
+    '',
+  ].join('\n'));
+  m.free();
+});
+
+test('it can be reset for a new input', async (t) => {
+  const p = new Parser();
+  p.enableTrace();
+  p.enableAccumulate();
+  p.parse(ENCODER.encode('FILE 0 a\nFUNC 1 2 3 f\nFU'));
+  p.finish();
+  p.reset();
+
+  p.parse(ENCODER.encode('FILE 3 b\nFUNC 5 6 7 g\n'));
+  p.finish();
+
+  t.is(p.takeTrace(), [
+    'file 3 b',
+    'func 5 6 7 g',
+    '',
+  ].join('\n'));
+  t.deepEqual(Array.from(p.getFiles()), [ 3, 0 ]);
+  t.is(p.funcCount(), 1);
+  p.free();
+});
+
+test('it reports raw lines after their records', async (t) => {
+  const result = await parse([
+    'MODULE Linux x86_64 ID a\r\nFUNC 1 2 0 f\n1 ',
+    '1 1 0\nABC 2 3 4\n',
+    'STACK CFI INIT 1 2 .cfa: $rsp 8 +',
+  ], {
+    callbacks: RAW_LINE_CALLBACKS,
+    before: p => p.enableRawLines(false),
+  });
+
+  t.deepEqual(result, [
+    [RecordKind.Module, 'MODULE Linux x86_64 ID a\r\n'],
+    ['func', 1, 2, 0, 'f'],
+    [RecordKind.Func, 'FUNC 1 2 0 f\n'],
+    ['line', 1, 1, 1, 0],
+    [RecordKind.Line, '1 1 1 0\n'],
+    [RecordKind.Other, 'ABC 2 3 4\n'],
+    [RecordKind.Stack, 'STACK CFI INIT 1 2 .cfa: $rsp 8 +'],
+  ]);
+});
+
+test('it reports only raw lines in exclusive mode', async (t) => {
+  const result = await parse(['FILE 0 a\n1 1 1 0\n'], {
+    callbacks: RAW_LINE_CALLBACKS,
+    before: p => p.enableRawLines(true),
+    after: p => t.is(p.lineCount(), 0),
+  });
+
+  t.deepEqual(result, [
+    [RecordKind.File, 'FILE 0 a\n'],
+    [RecordKind.Line, '1 1 1 0\n'],
+  ]);
+});
+
+test('it reports registered extension records', async (t) => {
+  let custom;
+  let fast;
+  const result = await parse([
+    'CUSTOM_FOO a b\r\nFA',
+    'ST 1\nFUNC 1 2 0 f\nFAST\nFASTER 2\n',
+  ], {
+    callbacks: result => ({
+      onExtension: (id, rest) => result.push([id, DECODER.decode(rest)]),
+    }),
+    before: p => {
+      custom = p.registerKeyword('CUSTOM_FOO');
+      fast = p.registerKeyword('FAST');
+      t.throws(() => p.registerKeyword('FUNC'));
+      t.throws(() => p.registerKeyword('lowercase'));
+      t.is(p.registerKeyword('CAFE'), 2);
+    },
+  });
+
+  t.deepEqual(result, [
+    [custom, 'a b'],
+    [fast, '1'],
+    ['func', 1, 2, 0, 'f'],
+    [fast, ''],
+  ]);
+});
+
+test('it parses FUNC, FILE and PUBLIC records the same with extensions', async (t) => {
+  const input = [
+    'FUNCX 1 2 3 f\nFUNC 4 5 6 g\nFIL',
+    'E 0 a\nFILEX 1 b\nPUBLIC 7 0 p\nPUBLICX 8 0 q\nF\nFU\n',
+  ];
+  const expected = [
+    ['func', 4, 5, 6, 'g'],
+    ['file', 0, 'a'],
+    ['public', 7, 0, 'p'],
+  ];
+
+  t.deepEqual(await parse(input), expected);
+  t.deepEqual(await parse(input, {
+    before: p => p.registerKeyword('FOO'),
+  }), expected);
+});
+
+test('it repairs invalid UTF-8 in names', async (t) => {
+  const repaired = [];
+  const p = new Parser({
+    ...NOOP_API,
+    onNameRepaired: offset => repaired.push(offset),
+  });
+  p.enableTrace();
+  p.enableUtf8Repair();
+  p.parse(new Uint8Array([
+    ...ENCODER.encode('FUNC 1 2 0 a'), 0xc3,
+  ]));
+  p.parse(new Uint8Array([
+    0xa9, ...ENCODER.encode('b\nFUNC 3 4 0 c'), 0xff, ...ENCODER.encode('d\n'),
+  ]));
+  p.finish();
+
+  t.is(p.takeTrace(), [
+    'func 1 2 0 a\u00e9b',
+    'func 3 4 0 c\ufffdd',
+    '',
+  ].join('\n'));
+  t.deepEqual(repaired, [ 16 ]);
+  p.free();
+});
+
+test('it stops parsing once the budget is spent', async (t) => {
+  const p = new Parser();
+  const input = ENCODER.encode(
+    Array.from({ length: 300 }, (_, i) => `FILE ${i} f\n`).join(''));
+
+  let offset = 0;
+  const consumed = [];
+  while (offset < input.length) {
+    const n = p.parseWithBudget(input.subarray(offset), 0);
+    consumed.push(n);
+    offset += n;
+  }
+  p.finish();
+
+  t.is(consumed.length, 2);
+  t.is(DECODER.decode(input.subarray(0, consumed[0])).split('\n').length, 257);
+  t.is(p.fileCount(), 300);
+  p.free();
+});
+
+test('it strips home directories from paths', async (t) => {
+  const s = new Scrubber();
+  const out = [
+    'MODULE Linux x86_64 ID a\nFILE 0 /home/alice/src/a.c\r\nFI',
+    'LE 1 /Users/bob/x.c\nFILE 2 C:\\Users\\eve\\p\\q.cc\n',
+    'FILE 3 relative/a.c\nFUNC 1 2 0 /home/alice/f\n',
+  ].map(chunk => DECODER.decode(s.scrub(ENCODER.encode(chunk))));
+  out.push(DECODER.decode(s.finish()));
+
+  t.is(out.join(''), [
+    'MODULE Linux x86_64 ID a',
+    'FILE 0 ~/src/a.c\r',
+    'FILE 1 ~/x.c',
+    'FILE 2 ~\\p\\q.cc',
+    'FILE 3 relative/a.c',
+    'FUNC 1 2 0 /home/alice/f',
+    '',
+  ].join('\n'));
+  s.free();
+});
+
+test('it replaces paths with salted hashes', async (t) => {
+  const s = new Scrubber('salt');
+  const out = DECODER.decode(s.scrub(ENCODER.encode('FILE 0 /src/a.c\n')));
+
+  const digest = createHash('sha256').update('salt/src/a.c').digest('hex');
+  t.is(out, `FILE 0 ${digest}\n`);
+  s.free();
+});
+
+test('it defers names behind string ids', async (t) => {
+  const result = [];
+  let strId;
+  const p = new Parser({
+    ...NOOP_API,
+    onFunc: (...args) => result.push(['func', ...args, strId]),
+    onPublic: (...args) => result.push(['public', ...args, strId]),
+    onStrValue: () => t.fail(),
+    onStrId: id => strId = id,
+  });
+  p.enableStringIds();
+  p.parse(ENCODER.encode('FUNC 1 2 0 my '));
+  p.parse(ENCODER.encode('func\nPUBLIC 4 0 pub\n'));
+  p.finish();
+
+  t.deepEqual(result, [
+    ['func', 1, 2, 0, 0],
+    ['public', 4, 0, 1],
+  ]);
+  t.is(DECODER.decode(p.getString(0)), 'my func');
+  t.is(p.getString(2), undefined);
+  t.deepEqual(p.getStringsById([1, 0]).map(s => DECODER.decode(s)),
+    ['pub', 'my func']);
+  t.throws(() => p.getStringsById([2]));
+  p.free();
+});
+
+test('it emits raw token events', async (t) => {
+  const result = [];
+  const tokenizer = new Tokenizer({
+    onToken: (kind, bytes) => result.push([kind, DECODER.decode(bytes)]),
+  });
+  tokenizer.tokenize(ENCODER.encode('FUNC 22226c 12c 0 bool  my_f'));
+  tokenizer.tokenize(ENCODER.encode('unc()\r\n\n2222'));
+  tokenizer.tokenize(ENCODER.encode('6c 1000 '));
+  tokenizer.tokenize(ENCODER.encode('\tCAFE 0'));
+  tokenizer.finish();
+
+  t.deepEqual(result, [
+    [TokenKind.Keyword, 'FUNC'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.Number, '22226c'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.Number, '12c'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.Number, '0'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.String, 'bool'],
+    [TokenKind.Separator, '  '],
+    [TokenKind.String, 'my_func()'],
+    [TokenKind.EndOfLine, ''],
+    [TokenKind.EndOfLine, ''],
+    [TokenKind.Number, '22226c'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.Number, '1000'],
+    [TokenKind.Separator, ' \t'],
+    [TokenKind.String, 'CAFE'],
+    [TokenKind.Separator, ' '],
+    [TokenKind.Number, '0'],
+    [TokenKind.EndOfLine, ''],
+  ]);
+  tokenizer.free();
+});
+
+test('it rejects saved states with invalid extension keywords', async (t) => {
+  const p = new Parser(NOOP_API);
+  p.registerKeyword('FOO');
+  const state = p.saveState();
+  p.free();
+
+  // Lowercase keywords are rejected by registerKeyword()
+  const offset = Buffer.from(state).indexOf('FOO');
+  t.not(offset, -1);
+  state.set(ENCODER.encode('foo'), offset);
+
+  const restored = new Parser(NOOP_API);
+  t.throws(() => restored.restoreState(state));
+  restored.parse(ENCODER.encode('FUNC 1 2 0 f\n'));
+  restored.finish();
+  t.is(restored.funcCount(), 1);
+  restored.free();
+});